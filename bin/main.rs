@@ -1,9 +1,11 @@
 use automata_lib::*;
+use log::{error, info};
 use std::cell::RefCell;
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
-use glam::u32::UVec3;
+use glam::u32::UVec2;
 
 use winit::{
     event::{Event, VirtualKeyCode, WindowEvent},
@@ -12,31 +14,225 @@ use winit::{
 };
 
 const FRAME_DELAY: Duration = Duration::new(0, 50000000);
+/* How often the file given with `--rule` is checked for changes */
+const RULE_POLL_INTERVAL: Duration = Duration::new(0, 500000000);
+
+/**
+ * The argument following `flag` on the command line, if `flag` was given.
+ */
+fn flag_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.next()?;
+    Some(
+        args.next()
+            .unwrap_or_else(|| panic!("{} expects a value", flag)),
+    )
+}
+
+/**
+ * The seed given with `--seed <seed>`, or a random one if there is none.
+ */
+fn initial_seed() -> u64 {
+    match flag_value("--seed") {
+        Some(seed) => seed
+            .parse()
+            .expect("--seed expects an unsigned 64 bit integer"),
+        None => rand::random(),
+    }
+}
+
+/**
+ * Parse the contents of a rule file: either a B/S rule string such as `B36/S23` or `B2/S/C3`, or a
+ * statement in the rule DSL, which runs as a two state rule on the Moore neighborhood.
+ */
+fn parse_rule(source: &str) -> Result<Rule, String> {
+    // Editors often truncate a file before writing it, and an empty statement kills every cell
+    if source.trim().is_empty() {
+        return Err("the file is empty".to_string());
+    }
+
+    let rule = match parse_rule_string(source) {
+        Ok(rule) => rule,
+        Err(rule_string_err) => match source.parse::<Statement>() {
+            Ok(statement) => Rule::from(statement).with_neighborhood(Neighborhood::Moore2D),
+            // Only DSL statements assign, so report the error of the format the file was meant to be in
+            Err(err) if source.contains('=') => return Err(err.to_string()),
+            Err(_) => return Err(rule_string_err.to_string()),
+        },
+    };
+    rule.validate().map_err(|err| err.to_string())?;
+    Ok(rule)
+}
+
+/**
+ * A rule file given with `--rule <path>`, reloaded whenever its modification time changes.
+ */
+struct RuleFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl RuleFile {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            modified: None,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /**
+     * Read and parse the file. Errors are logged rather than returned, so a half written or
+     * broken rule never stops the viewer.
+     */
+    fn load(&mut self) -> Option<Rule> {
+        self.modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let result = std::fs::read_to_string(&self.path)
+            .map_err(|err| err.to_string())
+            .and_then(|source| parse_rule(&source));
+        match result {
+            Ok(rule) => {
+                info!(
+                    "Loaded rule from {}: {}",
+                    self.path.display(),
+                    describe(&rule)
+                );
+                Some(rule)
+            }
+            Err(err) => {
+                error!("Could not load rule from {}: {}", self.path.display(), err);
+                None
+            }
+        }
+    }
+
+    /**
+     * Reload the file if it changed since it was last loaded, checking at most once every
+     * `RULE_POLL_INTERVAL`.
+     */
+    fn poll(&mut self) -> Option<Rule> {
+        if self.last_poll.elapsed() < RULE_POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.load()
+    }
+}
+
+fn describe(rule: &Rule) -> String {
+    match &rule.notation {
+        Some(notation) => notation.clone(),
+        None => rule.statement.to_string(),
+    }
+}
+
+/**
+ * Switch the automata being drawn to `rule` without resetting its cells. A rule with the same
+ * states and neighborhood only swaps the transition table; otherwise the automata and renderer are
+ * rebuilt from the current cells. Alive cells stay alive and any state the new rule doesn't have
+ * becomes dead. On failure the current rule keeps running.
+ */
+fn swap_rule(
+    renderer: &mut AutomataRenderer,
+    rule: Rule,
+    render_state: &RenderState,
+) -> Result<(), AutomataError> {
+    let device = &render_state.device;
+    let automata = &mut renderer.automata;
+
+    if rule.states == automata.rule.states
+        && rule.alive_state == automata.rule.alive_state
+        && rule.neighborhood == automata.rule.neighborhood
+    {
+        automata.set_rule(rule.statement.clone(), &render_state.queue)?;
+        automata.rule = rule;
+        return Ok(());
+    }
+
+    let cells: Vec<u32> = automata
+        .read_state(device, &render_state.queue)
+        .into_iter()
+        .map(|state| {
+            if state == automata.rule.alive_state {
+                rule.alive_state
+            } else if state > rule.max_state() {
+                0
+            } else {
+                state
+            }
+        })
+        .collect();
+    let swapped = Automata {
+        iteration: automata.iteration,
+        seed: automata.seed,
+        ..Automata::from_state(&automata.dim, &cells, rule, automata.boundaries, device)?
+    };
+    *renderer = AutomataRenderer::new(
+        device,
+        &render_state.general_bind_group_layout,
+        render_state.swapchain_format,
+        swapped,
+    )?;
+    Ok(())
+}
 
 async fn run(event_loop: EventLoop<()>, window: Window) {
-    let render_state = Rc::new(RefCell::new(RenderState::new(&window).await));
+    let render_state = match RenderState::new(&window).await {
+        Ok(render_state) => Rc::new(RefCell::new(render_state)),
+        Err(err) => {
+            error!("Could not set up rendering: {}", err);
+            return;
+        }
+    };
     let mut last_draw = Instant::now();
-    let automata_dim = UVec3::new(500, 500, 3);
+    let automata_dim = UVec2::new(500, 500);
     let automata_p = 0.02;
-    let automata_rules = rulesets::conways_game_of_life();
+    let mut rule_file = flag_value("--rule").map(|path| RuleFile::new(PathBuf::from(path)));
+    let automata_rules = rule_file
+        .as_mut()
+        .and_then(RuleFile::load)
+        .unwrap_or_else(|| {
+            Rule::from(rulesets::conways_game_of_life()).with_neighborhood(Neighborhood::Moore2D)
+        });
+
+    let mut seed = initial_seed();
 
     let render_ref = render_state.clone();
-    let fresh_automata = move || {
+    let fresh_automata = move |seed: u64, rule: Rule| {
+        info!("Seed: {}", seed);
         let render_ref = render_ref.borrow();
         AutomataRenderer::new(
             &render_ref.device,
             &render_ref.general_bind_group_layout,
             render_ref.swapchain_format,
-            Automata::new(
+            Automata::new_2d(
                 &automata_dim,
                 automata_p,
-                automata_rules.clone(),
+                seed,
+                rule,
+                Boundaries::all(Boundary::Wrap),
                 &render_ref.device,
-            ),
+            )?,
         )
     };
 
-    let mut automata_renderer = fresh_automata();
+    let mut automata_renderer = match fresh_automata(seed, automata_rules) {
+        Ok(automata_renderer) => automata_renderer,
+        Err(err) => {
+            error!("Could not create the automata: {}", err);
+            return;
+        }
+    };
 
     let mut since_last_update = FRAME_DELAY;
     let mut camera = SimpleCamera::new();
@@ -67,13 +263,14 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     },
                 ..
             } => {
-                use VirtualKeyCode::*;
-                match keycode {
-                    R => {
-                        // On 'R' reset the automata
-                        automata_renderer = fresh_automata();
+                if keycode == VirtualKeyCode::R {
+                    // On 'R' reset the automata with the next seed
+                    seed = seed.wrapping_add(1);
+                    let rule = automata_renderer.automata.rule.clone();
+                    match fresh_automata(seed, rule) {
+                        Ok(fresh) => automata_renderer = fresh,
+                        Err(err) => error!("Could not re-seed the automata: {}", err),
                     }
-                    _ => {}
                 }
                 camera.key(keycode, state);
             }
@@ -93,6 +290,12 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     .texture
                     .create_view(&wgpu::TextureViewDescriptor::default());
 
+                if let Some(rule) = rule_file.as_mut().and_then(RuleFile::poll) {
+                    if let Err(err) = swap_rule(&mut automata_renderer, rule, &render_state) {
+                        error!("Could not switch rule: {}", err);
+                    }
+                }
+
                 // If update delay has passed then use a compute
                 // pipeline to update the automata
                 since_last_update += elapsed;
@@ -4,28 +4,27 @@ var<storage, read> automata_dim: vec3<u32>;
 
 @group(0)
 @binding(1)
-var<storage, read> compute_offset_buffer: vec3<u32>;
-
-@group(0)
-@binding(2)
 var<storage, read_write> input_tensor: array<u32>;
 
 @group(0)
-@binding(3)
+@binding(2)
 var<storage, read_write> output_tensor: array<u32>;
 
-fn automata_id_to_pos(id: u32) -> vec3<u32> {
-    let automatas_in_layer: u32 = automata_dim.x * automata_dim.y;
-    let z = id / automatas_in_layer;
+// The rule compiled on the CPU, see `Rule::transition_table`
+struct TransitionTable {
+  // The state neighbors must be in to be counted
+  alive_state: u32,
+  // One entry per alive neighbor count, from zero to the size of the neighborhood
+  row_length: u32,
+  next_state: array<u32>,
+};
 
-    let id: u32 = id % automatas_in_layer;
-    let y = id / automata_dim.x;
-
-    let id: u32  = id % automata_dim.x;
-    let x = id;
+@group(0)
+@binding(3)
+var<storage, read> transition_table: TransitionTable;
 
-    return vec3<u32>(x, y, z);
-}
+// Copied out of the transition table once per invocation, it is read for every neighbor
+var<private> alive_state: u32;
 
 fn xyz_to_id(xyz: vec3<u32>) -> u32 {
     let z = (xyz.z * (automata_dim.x * automata_dim.y));
@@ -33,40 +32,82 @@ fn xyz_to_id(xyz: vec3<u32>) -> u32 {
     return xyz.x + y + z;
 }
 
-fn neighbors(offset: vec3<u32>) -> u32 {
-  // Skip the boundaries to avoid out of bounds weirdness
-  if offset.x == 0u || offset.y == 0u || offset.z == 0u ||
-     offset.x == automata_dim.x - 1u || offset.y == automata_dim.y - 1u ||
-     offset.z == automata_dim.z - 1u {
+// Two state grids are bit packed, 32 cells along x to a word with every row starting a new word
+fn packed_row_words() -> u32 {
+    return (automata_dim.x + 31u) / 32u;
+}
+
+fn packed_word_id(xyz: vec3<u32>) -> u32 {
+    return xyz.x / 32u + (xyz.y + xyz.z * automata_dim.y) * packed_row_words();
+}
+
+fn cell_state(xyz: vec3<u32>) -> u32 {
+    return input_tensor[xyz_to_id(xyz)];
+}
+
+fn packed_cell_state(xyz: vec3<u32>) -> u32 {
+    return (input_tensor[packed_word_id(xyz)] >> (xyz.x % 32u)) & 1u;
+}
+
+BOUNDARY_PLACEHOLDER
+
+// Whether the neighbor at pos + delta is alive, after applying the boundary of each axis
+fn neighbor_at(pos: vec3<u32>, delta: vec3<i32>) -> u32 {
+  let x = boundary_x(i32(pos.x) + delta.x, i32(automata_dim.x));
+  let y = boundary_y(i32(pos.y) + delta.y, i32(automata_dim.y));
+  let z = boundary_z(i32(pos.z) + delta.z, i32(automata_dim.z));
+
+  if x == -1 || y == -1 || z == -1 {
     return 0u;
-  } else {
-    var result: u32 = 0u;
-
-    for (var z: u32 = 0u; z < 3u; z += 1u) {
-      for (var y: u32 = 0u; y < 3u; y += 1u) { 
-        for (var x: u32 = 0u; x < 3u; x += 1u) {
-          let point_id: u32 = xyz_to_id(vec3<u32>(x, y, z) + offset - vec3<u32>(1u, 1u, 1u));
-          result += u32(input_tensor[point_id]);
-        }
-      }
-    }
-
-    return result;
   }
+
+  if x == -2 || y == -2 || z == -2 {
+    return 1u;
+  }
+
+  return u32(CELL_LOOKUP_PLACEHOLDER(vec3<u32>(u32(x), u32(y), u32(z))) == alive_state);
+}
+
+fn neighbors(pos: vec3<u32>) -> u32 {
+  NEIGHBORHOOD_PLACEHOLDER
+}
+
+// The state of the cell at pos in the next generation
+fn next_state(pos: vec3<u32>, state: u32) -> u32 {
+  return transition_table.next_state[state * transition_table.row_length + neighbors(pos)];
 }
 
 @compute
-@workgroup_size(1)
+@workgroup_size(WORKGROUP_SIZE_PLACEHOLDER)
 fn main(@builtin(global_invocation_id) pos: vec3<u32>) {
-  let id: u32 = compute_offset_buffer.x + pos.x;
-  let pos: vec3<u32> = automata_id_to_pos(id);
+  // Whole workgroups are dispatched, so some invocations fall past the edge of the grid
+  if pos.x >= automata_dim.x || pos.y >= automata_dim.y || pos.z >= automata_dim.z {
+    return;
+  }
+
+  alive_state = transition_table.alive_state;
+  let id: u32 = xyz_to_id(pos);
+  output_tensor[id] = next_state(pos, input_tensor[id]);
+}
 
-  let is_alive = input_tensor[id] > 0u;
-  let num_neighbors: u32 = neighbors(pos) - input_tensor[id];
+// Each invocation updates the 32 cells of one word of a bit packed grid
+@compute
+@workgroup_size(WORKGROUP_SIZE_PLACEHOLDER)
+fn main_packed(@builtin(global_invocation_id) word_pos: vec3<u32>) {
+  if word_pos.x >= packed_row_words() || word_pos.y >= automata_dim.y || word_pos.z >= automata_dim.z {
+    return;
+  }
 
-  var result: u32 = 0u;
+  alive_state = transition_table.alive_state;
+  let first = vec3<u32>(word_pos.x * 32u, word_pos.y, word_pos.z);
+  let cells = min(automata_dim.x - first.x, 32u);
+  let current = input_tensor[packed_word_id(first)];
+
+  var word: u32 = 0u;
+  for (var bit: u32 = 0u; bit < cells; bit = bit + 1u) {
+    let pos = vec3<u32>(first.x + bit, first.y, first.z);
+    word = word | (next_state(pos, (current >> bit) & 1u) << bit);
+  }
 
-  PLACEHOLDER
- 
-  output_tensor[id] = result;
+  output_tensor[packed_word_id(first)] = word;
 }
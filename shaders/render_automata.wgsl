@@ -19,6 +19,21 @@ var<storage, read> input_tensor: array<u32>;
 
 let NUM_VERTICES: u32 = 36u;
 
+fn cell_state(id: u32) -> u32 {
+    return input_tensor[id];
+}
+
+// Two state automata are bit packed, see packed_word_id in compute_automata.wgsl
+fn packed_cell_state(id: u32) -> u32 {
+    let x = id % automata_dim.x;
+    let row = id / automata_dim.x;
+    let word = x / 32u + row * ((automata_dim.x + 31u) / 32u);
+    return (input_tensor[word] >> (x % 32u)) & 1u;
+}
+
+// Planar automata only draw the first two triangles (the z = 0 face) of each cube
+let NUM_PLANAR_VERTICES: u32 = 6u;
+
 fn index_to_position(index: u32) -> vec4<f32> {
     let triangle_id: u32 = index / 3u; 
     let index = index % 3u;
@@ -104,15 +119,14 @@ fn automata_id_to_offset(id: u32, automata_state: u32) -> vec4<f32> {
     }
 } 
 
-@vertex
-fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+fn automata_vertex(vertex_index: u32, num_vertices: u32) -> VertexOutput {
 
     // The draw call will run a vertex shader on each block
-    // of the automata NUM_VERTICES times so we choose to draw
+    // of the automata num_vertices times so we choose to draw
     // or not draw a valid shape at each point.
-    let automata_id = vertex_index / NUM_VERTICES;
-    let vertex_id = vertex_index % NUM_VERTICES;
-    let automata_state: u32 = input_tensor[automata_id];
+    let automata_id = vertex_index / num_vertices;
+    let vertex_id = vertex_index % num_vertices;
+    let automata_state: u32 = CELL_LOOKUP_PLACEHOLDER(automata_id);
 
     let position_offset = automata_id_to_offset(automata_id, automata_state);
     let raw_position: vec4<f32> = index_to_position(vertex_id);
@@ -131,6 +145,16 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     return result;
 }
 
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    return automata_vertex(vertex_index, NUM_VERTICES);
+}
+
+@vertex
+fn vs_planar(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    return automata_vertex(vertex_index, NUM_PLANAR_VERTICES);
+}
+
 @fragment
 fn fs_main(
   @location(0) normal: vec3<f32>,
@@ -1,69 +1,177 @@
-use glam::u32::UVec3;
+use crate::automata_dsl::{Rule, Statement};
+use crate::automata_dsl_types::RuleError;
+use crate::automata_error::{validate_shader, AutomataError};
+use crate::bit_packing::{pack_cells, packed_row_words, packed_words, unpack_cells};
+use crate::boundary::Boundaries;
+use crate::neighborhood::Neighborhood;
+use crate::pattern::Pattern;
+use crate::pattern_file::{write_life_106, write_rle, UnsupportedStateError};
+use crate::snapshot::{Snapshot, SnapshotError};
+use crate::soup::random_soup;
+use glam::u32::{UVec2, UVec3};
 use log::info;
 use std::borrow::Cow;
 use std::cmp::min;
+use std::io::{Read, Write};
 use wgpu::{
     util::DeviceExt, BindGroup, BindGroupLayout, Buffer, ComputePipeline, Device, Queue,
     RenderPass, RenderPipeline, TextureFormat,
 };
 
 const NUM_VERTICES_PER_BLOCK: u32 = 36;
-const MAX_COMPUTE_PER_SHADER: u32 = 65535;
+const NUM_VERTICES_PER_SQUARE: u32 = 6;
+/* Each workgroup updates a block of cells, flat for single layer grids and a box otherwise */
+const WORKGROUP_SIZE_2D: UVec3 = UVec3::new(16, 16, 1);
+const WORKGROUP_SIZE_3D: UVec3 = UVec3::new(8, 8, 4);
+/* Long fast-forwards are split into submissions of at most this many generations so a single
+ * command buffer never runs long enough to trip a driver watchdog */
+const MAX_GENERATIONS_PER_SUBMISSION: usize = 1024;
+/* The alive state and row length stored in front of the transition table */
+const TRANSITION_TABLE_HEADER_LEN: u64 = 2;
 
 pub struct Automata {
     pub dim: UVec3,
     pub size: u32,
     pub pipeline: ComputePipeline,
     pub dim_buffer: Buffer,
-    pub compute_offset_buffer: Buffer,
+    pub workgroup_size: UVec3,
     pub buffers: [Buffer; 2],
+    /* The rule compiled to a transition table, see `transition_table_contents` */
+    pub rule_buffer: Buffer,
     pub bind_groups: Vec<BindGroup>,
     pub iteration: usize,
+    /* The seed of the random soup the automata started from, if it started from one */
+    pub seed: Option<u64>,
+    pub rule: Rule,
+    pub boundaries: Boundaries,
+    /* Two state automata store their cells bit packed, 32 to a word, see `pack_cells` */
+    pub packed: bool,
 }
 
 impl Automata {
-    pub fn new(dim: &UVec3, p: f32, dsl: crate::automata_dsl::Statement, device: &Device) -> Self {
-        let initial_state: Vec<u32> = (0..(dim.x * dim.y * dim.z))
-            .map(|_| if rand::random::<f32>() <= p { 1 } else { 0 })
-            .collect();
+    /**
+     * Create an automata of `dim` cells where each cell starts alive with probability `p`. The
+     * initial state is generated by `random_soup` from `seed`, so the same seed, dimensions and
+     * rule always give the same generations. Fails like `from_state`.
+     */
+    pub fn new(
+        dim: &UVec3,
+        p: f32,
+        seed: u64,
+        rule: Rule,
+        boundaries: Boundaries,
+        device: &Device,
+    ) -> Result<Self, AutomataError> {
+        cell_count(dim)?;
+        let initial_state = random_soup(dim, p, seed, rule.alive_state);
+        Ok(Self {
+            seed: Some(seed),
+            ..Self::from_state(dim, &initial_state, rule, boundaries, device)?
+        })
+    }
+
+    /**
+     * Create an automata of `dim` cells that starts in `initial_state`, which holds the state of
+     * every cell in id order (x, then y, then z). Rules with two states are stored bit packed,
+     * which takes 32 times less memory; every other method still takes and returns one u32 per
+     * cell.
+     *
+     * Fails if `dim` has no cells or more than `u32::MAX`, if the rule is invalid, if
+     * `initial_state` does not have one cell per position or has a cell past the rule's last
+     * state, if the grid or rule is too large for the device, or if the generated compute shader
+     * does not validate.
+     */
+    pub fn from_state(
+        dim: &UVec3,
+        initial_state: &[u32],
+        rule: Rule,
+        boundaries: Boundaries,
+        device: &Device,
+    ) -> Result<Self, AutomataError> {
+        check_state(dim, &rule, initial_state)?;
+        rule.validate()?;
 
-        let shader_rules = dsl.to_shader();
+        let packed = rule.states == 2;
 
-        let shader = include_str!("../shaders/compute_automata.wgsl")
-            .to_string()
-            .replace("PLACEHOLDER", &shader_rules);
+        let workgroup_size = if dim.z == 1 {
+            WORKGROUP_SIZE_2D
+        } else {
+            WORKGROUP_SIZE_3D
+        };
 
-        info!("Shader code: {}", shader_rules);
+        let limits = device.limits();
+        let check_limit = |limit, required: u64, supported: u32| {
+            if required > supported as u64 {
+                Err(AutomataError::LimitExceeded {
+                    limit,
+                    required,
+                    supported: supported as u64,
+                })
+            } else {
+                Ok(())
+            }
+        };
+
+        let workgroups = workgroup_count(dim, workgroup_size, packed);
+        check_limit(
+            "workgroups per dimension",
+            workgroups.max_element() as u64,
+            limits.max_compute_workgroups_per_dimension,
+        )?;
+
+        // Checked before building the table, which for a rule with many states may not fit in memory
+        check_limit(
+            "transition table size (bytes)",
+            (TRANSITION_TABLE_HEADER_LEN
+                + rule.states as u64 * rule.transition_row_length() as u64)
+                * std::mem::size_of::<u32>() as u64,
+            limits.max_storage_buffer_binding_size,
+        )?;
+        let transition_table = transition_table_contents(&rule);
+
+        let shader = compute_shader(workgroup_size, &rule.neighborhood, &boundaries, packed);
+        validate_shader(&shader)?;
+
+        info!("Transition table: {:?}", transition_table);
 
         let cs_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
             source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&shader)),
         });
 
-        let slice_size = initial_state.len() * std::mem::size_of::<u32>();
-        let size = slice_size as wgpu::BufferAddress;
-
-        let automata_dim_buffer = crate::util::uvec_buffer(device, &dim);
+        let storage = if packed {
+            pack_cells(dim, initial_state)
+        } else {
+            initial_state.to_vec()
+        };
+        let size = std::mem::size_of_val(storage.as_slice()) as wgpu::BufferAddress;
+        check_limit(
+            "cell buffer size (bytes)",
+            size,
+            limits.max_storage_buffer_binding_size,
+        )?;
 
-        let compute_offset_buffer = crate::util::uvec_buffer(device, &UVec3::new(0, 0, 0));
+        let automata_dim_buffer = crate::util::uvec_buffer(device, dim);
 
         let automata_buffers = [
             device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Automata Tensor 1"),
-                contents: bytemuck::cast_slice(&initial_state),
+                contents: bytemuck::cast_slice(&storage),
                 usage: wgpu::BufferUsages::STORAGE
                     | wgpu::BufferUsages::COPY_DST
                     | wgpu::BufferUsages::COPY_SRC,
             }),
             device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Automata Tensor 2"),
-                contents: bytemuck::cast_slice(&initial_state),
+                contents: bytemuck::cast_slice(&storage),
                 usage: wgpu::BufferUsages::STORAGE
                     | wgpu::BufferUsages::COPY_DST
                     | wgpu::BufferUsages::COPY_SRC,
             }),
         ];
 
+        let rule_buffer = create_rule_buffer(device, &transition_table);
+
         let uvec3_layout = |i| wgpu::BindGroupLayoutEntry {
             binding: i,
             visibility: wgpu::ShaderStages::COMPUTE,
@@ -90,9 +198,21 @@ impl Automata {
             label: None,
             entries: &[
                 uvec3_layout(0),
-                uvec3_layout(1),
+                tensor_layout(1),
                 tensor_layout(2),
-                tensor_layout(3),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        /* The header and at least one entry, the table grows with the rule */
+                        min_binding_size: wgpu::BufferSize::new(
+                            (std::mem::size_of::<u32>() * 3) as u64,
+                        ),
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -106,75 +226,419 @@ impl Automata {
             label: Some("Automata compute pipeline"),
             layout: Some(&pipeline_layout),
             module: &cs_module,
-            entry_point: "main",
+            entry_point: if packed { "main_packed" } else { "main" },
         });
 
         let bind_group_layout = pipeline.get_bind_group_layout(0);
 
-        let bind_groups: Vec<BindGroup> = (0..2)
-            .map(|offset| {
-                device.create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: None,
-                    layout: &bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: automata_dim_buffer.as_entire_binding(),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: compute_offset_buffer.as_entire_binding(),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 2,
-                            resource: automata_buffers[offset].as_entire_binding(),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 3,
-                            resource: automata_buffers[(offset + 1) % 2].as_entire_binding(),
-                        },
-                    ],
-                })
-            })
-            .collect();
+        let bind_groups = create_bind_groups(
+            device,
+            &bind_group_layout,
+            &automata_dim_buffer,
+            &automata_buffers,
+            &rule_buffer,
+        );
 
-        Self {
+        Ok(Self {
             dim: *dim,
             dim_buffer: automata_dim_buffer,
             buffers: automata_buffers,
-            compute_offset_buffer,
+            rule_buffer,
+            workgroup_size,
             pipeline,
             bind_groups,
             iteration: 0,
-            size: dim.x * dim.y * dim.z,
+            seed: None,
+            rule,
+            boundaries,
+            packed,
+            size: initial_state.len() as u32,
+        })
+    }
+
+    /**
+     * Create an automata of `dim` cells where the cells at `live_cells` start in the rule's alive
+     * state and every other cell starts dead. Fails like `from_state`, or if a live cell is outside
+     * the grid.
+     */
+    pub fn from_live_cells(
+        dim: &UVec3,
+        live_cells: &[UVec3],
+        rule: Rule,
+        boundaries: Boundaries,
+        device: &Device,
+    ) -> Result<Self, AutomataError> {
+        let mut initial_state = vec![0; cell_count(dim)? as usize];
+        for cell in live_cells {
+            if !cell.cmplt(*dim).all() {
+                return Err(AutomataError::OutOfBounds {
+                    position: *cell,
+                    size: UVec3::ONE,
+                    dim: *dim,
+                });
+            }
+            initial_state[(cell.x + cell.y * dim.x + cell.z * dim.x * dim.y) as usize] =
+                rule.alive_state;
+        }
+        Self::from_state(dim, &initial_state, rule, boundaries, device)
+    }
+
+    /**
+     * Create an automata of `dim` dead cells with `pattern` placed at `offset`. Fails like
+     * `from_state`, or if the pattern does not fit in the grid at `offset`.
+     */
+    pub fn from_pattern(
+        dim: &UVec3,
+        pattern: &Pattern,
+        offset: UVec3,
+        rule: Rule,
+        boundaries: Boundaries,
+        device: &Device,
+    ) -> Result<Self, AutomataError> {
+        let cells = cell_count(dim)?;
+        if !pattern.fits(dim, offset) {
+            return Err(AutomataError::OutOfBounds {
+                position: offset,
+                size: UVec3::new(pattern.width, pattern.height, 1),
+                dim: *dim,
+            });
+        }
+
+        let mut initial_state = vec![0; cells as usize];
+        pattern.place(dim, offset, &mut initial_state);
+        Self::from_state(dim, &initial_state, rule, boundaries, device)
+    }
+
+    /**
+     * Create a planar automata: a single layer of `dim.x` by `dim.y` cells. The rule must use a
+     * 2D neighborhood (e.g. `Neighborhood::Moore2D` for classic life-like rules), and the renderer
+     * draws the automata as a flat plane rather than as cubes. The z boundary is never reached.
+     * Fails like `new`, or if the rule's neighborhood is not planar.
+     */
+    pub fn new_2d(
+        dim: &UVec2,
+        p: f32,
+        seed: u64,
+        rule: Rule,
+        boundaries: Boundaries,
+        device: &Device,
+    ) -> Result<Self, AutomataError> {
+        if !rule.neighborhood.is_planar() {
+            return Err(AutomataError::NotPlanar(rule.neighborhood));
+        }
+
+        Self::new(&dim.extend(1), p, seed, rule, boundaries, device)
+    }
+
+    /**
+     * True if the automata is a single layer of cells.
+     */
+    pub fn is_2d(&self) -> bool {
+        self.dim.z == 1
+    }
+
+    /**
+     * Overwrite the state of every cell. Both ping-pong buffers are written, so the next update
+     * and the renderer both see the new state. The iteration count is left alone. Fails, leaving
+     * the cells untouched, if `state` does not fit the automata like in `from_state`.
+     */
+    pub fn write_state(&self, queue: &Queue, state: &[u32]) -> Result<(), AutomataError> {
+        check_state(&self.dim, &self.rule, state)?;
+        for buffer in &self.buffers {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&self.to_storage(state)));
+        }
+        Ok(())
+    }
+
+    /**
+     * The buffer holding the current generation, which the next update reads from.
+     */
+    pub fn current_buffer(&self) -> &Buffer {
+        &self.buffers[self.iteration % 2]
+    }
+
+    /**
+     * Copy the current generation into a new buffer that can be mapped for reading.
+     */
+    fn copy_to_staging_buffer(&self, device: &Device, queue: &Queue) -> Buffer {
+        let size = self.storage_size();
+
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Automata staging buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_buffer_to_buffer(self.current_buffer(), 0, &staging_buffer, 0, size);
+        queue.submit(Some(encoder.finish()));
+
+        staging_buffer
+    }
+
+    /**
+     * Read the cells out of a mapped staging buffer and unmap it.
+     */
+    fn read_mapped(&self, staging_buffer: Buffer) -> Vec<u32> {
+        let storage: Vec<u32> =
+            bytemuck::cast_slice(&staging_buffer.slice(..).get_mapped_range()).to_vec();
+        staging_buffer.unmap();
+        if self.packed {
+            unpack_cells(&self.dim, &storage)
+        } else {
+            storage
+        }
+    }
+
+    /**
+     * The cells in the layout of the automata buffers.
+     */
+    fn to_storage(&self, cells: &[u32]) -> Vec<u32> {
+        if self.packed {
+            pack_cells(&self.dim, cells)
+        } else {
+            cells.to_vec()
+        }
+    }
+
+    /**
+     * The size in bytes of each of the automata buffers.
+     */
+    pub fn storage_size(&self) -> wgpu::BufferAddress {
+        let words = if self.packed {
+            packed_words(&self.dim)
+        } else {
+            self.size as usize
+        };
+        (words * std::mem::size_of::<u32>()) as wgpu::BufferAddress
+    }
+
+    /**
+     * Copy the current generation back from the GPU, blocking until it arrives. Cells are in id
+     * order (x, then y, then z). This waits on the device, so it only works on native targets;
+     * use `read_state_async` on the web.
+     */
+    pub fn read_state(&self, device: &Device, queue: &Queue) -> Vec<u32> {
+        let staging_buffer = self.copy_to_staging_buffer(device, queue);
+
+        staging_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| {
+                result.expect("Failed to map the automata staging buffer")
+            });
+        device.poll(wgpu::Maintain::Wait);
+
+        self.read_mapped(staging_buffer)
+    }
+
+    /**
+     * Copy the current generation back from the GPU, resolving once it arrives. On native
+     * targets this polls the device until the copy is done, on the web the browser maps the
+     * buffer in the background.
+     */
+    pub async fn read_state_async(&self, device: &Device, queue: &Queue) -> Vec<u32> {
+        let staging_buffer = self.copy_to_staging_buffer(device, queue);
+
+        let (sender, receiver) = futures_intrusive::channel::shared::oneshot_channel();
+        staging_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                sender.send(result).ok();
+            });
+        device.poll(wgpu::Maintain::Wait);
+
+        receiver
+            .receive()
+            .await
+            .expect("The automata staging buffer was dropped while mapping")
+            .expect("Failed to map the automata staging buffer");
+
+        self.read_mapped(staging_buffer)
+    }
+
+    /**
+     * The current generation as one pattern per z layer, each cropped to its live cells and
+     * carrying the rule's notation as its rule string.
+     */
+    pub fn layers(&self, device: &Device, queue: &Queue) -> Vec<Pattern> {
+        let state = self.read_state(device, queue);
+        (0..self.dim.z)
+            .map(|z| Pattern {
+                rule_string: self.rule.notation.clone(),
+                ..Pattern::from_layer(&self.dim, &state, z).cropped()
+            })
+            .collect()
+    }
+
+    /**
+     * The current generation in RLE format, one file per z layer. See `layers`. Fails if a cell is
+     * in a state past `MAX_RLE_STATE`, which rules with more states can reach.
+     */
+    pub fn export_rle(
+        &self,
+        device: &Device,
+        queue: &Queue,
+    ) -> Result<Vec<String>, UnsupportedStateError> {
+        self.layers(device, queue).iter().map(write_rle).collect()
+    }
+
+    /**
+     * The current generation in Life 1.06 format, one file per z layer. See `layers`.
+     */
+    pub fn export_life_106(&self, device: &Device, queue: &Queue) -> Vec<String> {
+        self.layers(device, queue)
+            .iter()
+            .map(write_life_106)
+            .collect()
+    }
+
+    /**
+     * Capture the current generation along with everything needed to resume from it.
+     */
+    pub fn snapshot(&self, device: &Device, queue: &Queue) -> Snapshot {
+        Snapshot {
+            dim: self.dim,
+            iteration: self.iteration,
+            seed: self.seed,
+            boundaries: self.boundaries,
+            rule: self.rule.clone(),
+            cells: self.read_state(device, queue),
+        }
+    }
+
+    /**
+     * Write a snapshot of the current generation in the format described by `Snapshot`.
+     */
+    pub fn save_snapshot(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        writer: &mut impl Write,
+    ) -> Result<(), SnapshotError> {
+        Ok(self.snapshot(device, queue).write_to(writer)?)
+    }
+
+    /**
+     * Create an automata that resumes from a snapshot written by `save_snapshot`.
+     */
+    pub fn from_snapshot(reader: &mut impl Read, device: &Device) -> Result<Self, SnapshotError> {
+        let snapshot = Snapshot::read_from(reader)?;
+        Ok(Self {
+            iteration: snapshot.iteration,
+            seed: snapshot.seed,
+            ..Self::from_state(
+                &snapshot.dim,
+                &snapshot.cells,
+                snapshot.rule,
+                snapshot.boundaries,
+                device,
+            )?
+        })
+    }
+
+    /**
+     * Resume this automata from a snapshot written by `save_snapshot`. The snapshot must have
+     * the same dimensions, rule and boundaries as the automata, as those are built into its
+     * buffers and compute shader.
+     */
+    pub fn load_snapshot(
+        &mut self,
+        queue: &Queue,
+        reader: &mut impl Read,
+    ) -> Result<(), SnapshotError> {
+        let snapshot = Snapshot::read_from(reader)?;
+
+        if snapshot.dim != self.dim {
+            return Err(SnapshotError::DimensionMismatch {
+                expected: self.dim,
+                found: snapshot.dim,
+            });
         }
+
+        if snapshot.boundaries != self.boundaries {
+            return Err(SnapshotError::SettingsMismatch(format!(
+                "the snapshot uses the boundaries {:?} but the automata uses {:?}",
+                snapshot.boundaries, self.boundaries
+            )));
+        }
+
+        // The notation is only a description, so it does not need to match
+        let rule = &snapshot.rule;
+        if rule.statement != self.rule.statement
+            || rule.states != self.rule.states
+            || rule.alive_state != self.rule.alive_state
+            || rule.neighborhood != self.rule.neighborhood
+        {
+            return Err(SnapshotError::SettingsMismatch(
+                "the snapshot uses a different rule than the automata".to_string(),
+            ));
+        }
+
+        self.write_state(queue, &snapshot.cells)?;
+        self.iteration = snapshot.iteration;
+        self.seed = snapshot.seed;
+        Ok(())
+    }
+
+    /**
+     * Switch to running `statement` without touching the cells, keeping the current number of
+     * states, alive state and neighborhood. Those fix the size of the transition table, so the new
+     * table is written over the old one in place and the pipeline, buffers and bind groups are all
+     * reused; the rule can be changed mid-run to see how a pattern responds. The write lands
+     * before the next `step`. An invalid statement leaves the current rule in place.
+     */
+    pub fn set_rule(&mut self, statement: Statement, queue: &Queue) -> Result<(), RuleError> {
+        let rule = Rule {
+            statement,
+            notation: None,
+            ..self.rule.clone()
+        };
+        rule.validate()?;
+
+        queue.write_buffer(
+            &self.rule_buffer,
+            0,
+            bytemuck::cast_slice(&transition_table_contents(&rule)),
+        );
+        self.rule = rule;
+        Ok(())
     }
 
+    /**
+     * Advance one generation.
+     */
     pub fn update(&mut self, device: &Device, queue: &Queue) {
-        let bind_group = self.iteration % 2;
-        self.iteration = self.iteration + 1;
+        self.step(device, queue, 1);
+    }
 
-        let bind_group = &self.bind_groups[bind_group];
+    /**
+     * Advance `generations` generations. Every generation is a single dispatch covering the grid
+     * in workgroups of `workgroup_size` cells, and all of them are recorded into one compute pass,
+     * so a fast-forward takes a single submission per `MAX_GENERATIONS_PER_SUBMISSION`
+     * generations.
+     */
+    pub fn step(&mut self, device: &Device, queue: &Queue, generations: usize) {
+        let workgroups = workgroup_count(&self.dim, self.workgroup_size, self.packed);
 
-        let dim_size = self.dim.x * self.dim.y * self.dim.z;
-        let step_size = min(dim_size, MAX_COMPUTE_PER_SHADER);
+        let mut remaining = generations;
+        while remaining > 0 {
+            let batch = min(remaining, MAX_GENERATIONS_PER_SUBMISSION);
+            remaining -= batch;
 
-        for offset in (0..dim_size as usize).step_by(step_size as usize) {
-            let offset = offset as u32;
-            queue.write_buffer(
-                &self.compute_offset_buffer,
-                0,
-                bytemuck::cast_slice(UVec3::new(offset, 0, 0).as_ref()),
-            );
             let mut encoder =
                 device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
             {
                 let mut cpass =
                     encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
                 cpass.set_pipeline(&self.pipeline);
-                cpass.set_bind_group(0, bind_group, &[]);
-                let id = min(step_size, dim_size - offset);
-                cpass.dispatch_workgroups(id, 1, 1);
+
+                for _ in 0..batch {
+                    cpass.set_bind_group(0, &self.bind_groups[self.iteration % 2], &[]);
+                    cpass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+                    self.iteration += 1;
+                }
             }
 
             queue.submit(Some(encoder.finish()));
@@ -182,8 +646,152 @@ impl Automata {
     }
 }
 
+/**
+ * The number of workgroups dispatched along each axis to update every cell once.
+ */
+fn workgroup_count(dim: &UVec3, workgroup_size: UVec3, packed: bool) -> UVec3 {
+    UVec3::new(
+        // Packed automata update a word of 32 cells along x per invocation
+        if packed {
+            packed_row_words(dim).div_ceil(workgroup_size.x)
+        } else {
+            dim.x.div_ceil(workgroup_size.x)
+        },
+        dim.y.div_ceil(workgroup_size.y),
+        dim.z.div_ceil(workgroup_size.z),
+    )
+}
+
+/**
+ * The compute shader for an automata with the given neighborhood and boundaries, updating blocks
+ * of `workgroup_size` cells, or words of packed cells if `packed`.
+ */
+fn compute_shader(
+    workgroup_size: UVec3,
+    neighborhood: &Neighborhood,
+    boundaries: &Boundaries,
+    packed: bool,
+) -> String {
+    include_str!("../shaders/compute_automata.wgsl")
+        .to_string()
+        .replace(
+            "WORKGROUP_SIZE_PLACEHOLDER",
+            &format!(
+                "{}, {}, {}",
+                workgroup_size.x, workgroup_size.y, workgroup_size.z
+            ),
+        )
+        .replace("BOUNDARY_PLACEHOLDER", &boundaries.to_shader())
+        .replace(
+            "NEIGHBORHOOD_PLACEHOLDER",
+            &neighborhood.to_shader(boundaries),
+        )
+        .replace(
+            "CELL_LOOKUP_PLACEHOLDER",
+            if packed {
+                "packed_cell_state"
+            } else {
+                "cell_state"
+            },
+        )
+}
+
+/**
+ * The contents of the transition table buffer the compute shader indexes: the alive state, the
+ * length of each row, then `Rule::transition_table`.
+ */
+fn transition_table_contents(rule: &Rule) -> Vec<u32> {
+    let mut contents = vec![rule.alive_state, rule.transition_row_length()];
+    contents.extend(rule.transition_table());
+    contents
+}
+
+fn create_rule_buffer(device: &Device, transition_table: &[u32]) -> Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Automata Transition Table"),
+        contents: bytemuck::cast_slice(transition_table),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+/**
+ * The two bind groups the automata alternates between, each reading one cell buffer and writing
+ * the other.
+ */
+fn create_bind_groups(
+    device: &Device,
+    layout: &BindGroupLayout,
+    dim_buffer: &Buffer,
+    buffers: &[Buffer; 2],
+    rule_buffer: &Buffer,
+) -> Vec<BindGroup> {
+    (0..2)
+        .map(|offset| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: dim_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: buffers[offset].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: buffers[(offset + 1) % 2].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: rule_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        })
+        .collect()
+}
+
+/**
+ * The number of cells in a grid of `dim`. Cells are addressed by a u32 id, so fails if the grid
+ * has more than `u32::MAX` cells, or none at all.
+ */
+pub(crate) fn cell_count(dim: &UVec3) -> Result<u32, AutomataError> {
+    // The product of three u32s may not even fit in a u64
+    let cells = (dim.x as u64)
+        .checked_mul(dim.y as u64)
+        .and_then(|cells| cells.checked_mul(dim.z as u64));
+    match cells {
+        Some(cells) if cells > 0 && cells <= u32::MAX as u64 => Ok(cells as u32),
+        _ => Err(AutomataError::InvalidDimensions(*dim)),
+    }
+}
+
+/**
+ * Check that `dim` is a valid grid, `state` has one cell for every position in it and every cell
+ * is a state of `rule`.
+ */
+pub(crate) fn check_state(dim: &UVec3, rule: &Rule, state: &[u32]) -> Result<(), AutomataError> {
+    let expected = cell_count(dim)? as usize;
+    if state.len() != expected {
+        return Err(AutomataError::StateSizeMismatch {
+            expected,
+            found: state.len(),
+        });
+    }
+    match state.iter().find(|cell| **cell > rule.max_state()) {
+        Some(cell) => Err(AutomataError::InvalidState {
+            state: *cell,
+            max_state: rule.max_state(),
+        }),
+        None => Ok(()),
+    }
+}
+
 pub struct AutomataRenderer {
     pub pipeline: RenderPipeline,
+    pub vertices_per_cell: u32,
     pub swapchain_format: TextureFormat,
     pub bind_groups: Vec<BindGroup>,
     pub automata: Automata,
@@ -195,12 +803,20 @@ impl AutomataRenderer {
         bind_group_layout: &BindGroupLayout,
         swapchain_format: TextureFormat,
         automata: Automata,
-    ) -> Self {
+    ) -> Result<Self, AutomataError> {
+        let shader = include_str!("../shaders/render_automata.wgsl").replace(
+            "CELL_LOOKUP_PLACEHOLDER",
+            if automata.packed {
+                "packed_cell_state"
+            } else {
+                "cell_state"
+            },
+        );
+        validate_shader(&shader)?;
+
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
-                "../shaders/render_automata.wgsl"
-            ))),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&shader)),
         });
 
         let uvec3_layout = |i| wgpu::BindGroupLayoutEntry {
@@ -220,7 +836,7 @@ impl AutomataRenderer {
             ty: wgpu::BindingType::Buffer {
                 ty: wgpu::BufferBindingType::Storage { read_only: true },
                 has_dynamic_offset: false,
-                min_binding_size: wgpu::BufferSize::new(automata.size as u64),
+                min_binding_size: wgpu::BufferSize::new(automata.storage_size()),
             },
             count: None,
         };
@@ -233,7 +849,7 @@ impl AutomataRenderer {
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[&bind_group_layout, &automata_bind_group_layout],
+            bind_group_layouts: &[bind_group_layout, &automata_bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -256,12 +872,28 @@ impl AutomataRenderer {
             })
             .collect();
 
+        // 2D automata are drawn as a single square per cell instead of a cube
+        let (vertex_entry_point, vertices_per_cell) = if automata.is_2d() {
+            ("vs_planar", NUM_VERTICES_PER_SQUARE)
+        } else {
+            ("vs_main", NUM_VERTICES_PER_BLOCK)
+        };
+        // Every cell is drawn from one range of vertex indices, so they must all fit in a u32
+        let vertices = automata.size as u64 * vertices_per_cell as u64;
+        if vertices > u32::MAX as u64 {
+            return Err(AutomataError::LimitExceeded {
+                limit: "vertices drawn",
+                required: vertices,
+                supported: u32::MAX as u64,
+            });
+        }
+
         let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
             layout: Some(&pipeline_layout),
             vertex: wgpu::VertexState {
                 module: &shader,
-                entry_point: "vs_main",
+                entry_point: vertex_entry_point,
                 buffers: &[],
             },
             fragment: Some(wgpu::FragmentState {
@@ -284,17 +916,84 @@ impl AutomataRenderer {
             multiview: None,
         });
 
-        Self {
+        Ok(Self {
             automata,
             pipeline,
+            vertices_per_cell,
             swapchain_format,
             bind_groups,
-        }
+        })
     }
 
     pub fn draw<'pass, 'automata: 'pass>(&'automata self, pass: &mut RenderPass<'pass>) {
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(1, &self.bind_groups[self.automata.iteration % 2], &[]);
-        pass.draw(0..self.automata.size * NUM_VERTICES_PER_BLOCK, 0..1);
+        pass.draw(0..self.automata.size * self.vertices_per_cell, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata_dsl::rulesets;
+    use crate::boundary::Boundary;
+    use glam::IVec3;
+
+    #[test]
+    fn transition_table_follows_its_header() {
+        let rule = Rule {
+            alive_state: 2,
+            ..rulesets::star_wars().with_neighborhood(Neighborhood::VonNeumann2D)
+        };
+        let contents = transition_table_contents(&rule);
+        assert_eq!(contents.len() as u64, TRANSITION_TABLE_HEADER_LEN + 4 * 5);
+        assert_eq!(contents[..2], [2, 5]);
+        assert_eq!(contents[2..], rule.transition_table());
+    }
+
+    #[test]
+    fn generated_compute_shaders_validate() {
+        let neighborhoods = [
+            Neighborhood::Moore2D,
+            Neighborhood::VonNeumann2D,
+            Neighborhood::Moore3D,
+            Neighborhood::VonNeumann3D,
+            Neighborhood::Box2D(2),
+            Neighborhood::Box3D(2),
+            Neighborhood::Diamond2D(2),
+            Neighborhood::Diamond3D(2),
+            Neighborhood::Offsets(vec![IVec3::new(-2, 0, 1), IVec3::new(0, 3, 0)]),
+        ];
+        let boundaries = [
+            Boundary::Skip,
+            Boundary::Wrap,
+            Boundary::Reflect,
+            Boundary::Dead,
+            Boundary::Alive,
+        ];
+        for neighborhood in &neighborhoods {
+            for (i, boundary) in boundaries.into_iter().enumerate() {
+                // Across the loop, mixed boundaries put every boundary on every axis
+                let mixed = Boundaries {
+                    x: boundary,
+                    y: boundaries[(i + 1) % boundaries.len()],
+                    z: boundaries[(i + 2) % boundaries.len()],
+                };
+                for boundaries in [Boundaries::all(boundary), mixed] {
+                    for workgroup_size in [WORKGROUP_SIZE_2D, WORKGROUP_SIZE_3D] {
+                        for packed in [false, true] {
+                            let shader =
+                                compute_shader(workgroup_size, neighborhood, &boundaries, packed);
+                            if let Err(err) = validate_shader(&shader) {
+                                panic!(
+                                    "{:?} with {:?}, packed {}: {}",
+                                    neighborhood, boundaries, packed, err
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
     }
 }
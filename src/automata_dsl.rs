@@ -1,11 +1,16 @@
 /**
  * An expression in the domain specific language we use to describe cellular automata. Expressions
- * can perform arbitrary arithmetic and comparisons between constants, a boolean that indicates
- * whether the cell is currently alive, and the number of neighbors that a cell currently has.
+ * can perform arbitrary arithmetic and comparisons between constants, the current state of the
+ * cell, a boolean that indicates whether the cell is currently alive (in the rule's alive state),
+ * and the number of neighbors that are currently alive.
+ *
+ * Arithmetic is on u32s. `Add` and `Mul` wrap on overflow, `Sub` saturates at 0 rather than
+ * underflowing, and `Div` and `Mod` by zero produce 0.
  */
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Expr {
     U32(u32),
+    State,
     Alive,
     Neighbors,
     Gt(Box<Expr>, Box<Expr>),
@@ -15,48 +20,139 @@ pub enum Expr {
     And(Box<Expr>, Box<Expr>),
     Or(Box<Expr>, Box<Expr>),
     Equal(Box<Expr>, Box<Expr>),
+    NotEqual(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Mod(Box<Expr>, Box<Expr>),
+    Min(Box<Expr>, Box<Expr>),
+    Max(Box<Expr>, Box<Expr>),
+    /* Select(condition, if_true, if_false) */
+    Select(Box<Expr>, Box<Expr>, Box<Expr>),
 }
 
+use crate::automata_dsl_types::RuleError;
+use crate::neighborhood::Neighborhood;
+use std::fmt;
 use Expr::*;
 
 impl Expr {
     /**
-     * This method converts an Expr to an equivalent wgsl code fragment. This is not a valid wgsl
-     * program, just an expression in wgsl. When used by statements it can form a complete wgsl
-     * program.
+     * The binding strength of this expression in the textual DSL. Higher binds tighter. Used by the
+     * printer to decide where parentheses are needed so that printed rules parse back unchanged.
      */
-    pub fn to_shader(&self) -> String {
+    fn precedence(&self) -> u8 {
         match self {
-            U32(val) => format!("{}u", val),
-            Alive => format!("is_alive"),
-            Neighbors => format!("num_neighbors"),
-            Gt(lhs, rhs) => format!(
-                "u32(({}) > ({}))",
-                Self::to_shader(lhs),
-                Self::to_shader(rhs)
-            ),
-            Gte(lhs, rhs) => format!(
-                "u32(({}) >= ({}))",
-                Self::to_shader(lhs),
-                Self::to_shader(rhs)
-            ),
-            Lt(lhs, rhs) => format!(
-                "u32(({}) < ({}))",
-                Self::to_shader(lhs),
-                Self::to_shader(rhs)
-            ),
-            Lte(lhs, rhs) => format!(
-                "u32(({}) <= ({}))",
-                Self::to_shader(lhs),
-                Self::to_shader(rhs)
-            ),
-            And(lhs, rhs) => format!("(({}) & ({}))", Self::to_shader(lhs), Self::to_shader(rhs)),
-            Or(lhs, rhs) => format!("(({}) | ({}))", Self::to_shader(lhs), Self::to_shader(rhs)),
-            Equal(lhs, rhs) => format!(
-                "u32(({}) == ({}))",
-                Self::to_shader(lhs),
-                Self::to_shader(rhs)
-            ),
+            Or(_, _) => 1,
+            And(_, _) => 2,
+            Equal(_, _) | NotEqual(_, _) => 3,
+            Gt(_, _) | Gte(_, _) | Lt(_, _) | Lte(_, _) => 4,
+            Add(_, _) | Sub(_, _) => 5,
+            Mul(_, _) | Div(_, _) | Mod(_, _) => 6,
+            Not(_) => 7,
+            U32(_) | State | Alive | Neighbors | Min(_, _) | Max(_, _) | Select(_, _, _) => 8,
+        }
+    }
+
+    /**
+     * Evaluate the expression on the CPU for a two state cell with the given alive flag and
+     * neighbor count. Booleans are represented as 0 or 1, `And` and `Or` are bitwise and
+     * comparisons produce 0 or 1.
+     */
+    pub fn eval(&self, alive: bool, neighbors: u32) -> u32 {
+        self.eval_with_state(alive as u32, alive, neighbors)
+    }
+
+    /**
+     * Evaluate the expression on the CPU for a cell in an arbitrary state. `alive` should be
+     * whether `state` is the rule's alive state.
+     */
+    pub fn eval_with_state(&self, state: u32, alive: bool, neighbors: u32) -> u32 {
+        let eval = |expr: &Expr| expr.eval_with_state(state, alive, neighbors);
+        match self {
+            U32(val) => *val,
+            State => state,
+            Alive => alive as u32,
+            Neighbors => neighbors,
+            Gt(lhs, rhs) => (eval(lhs) > eval(rhs)) as u32,
+            Gte(lhs, rhs) => (eval(lhs) >= eval(rhs)) as u32,
+            Lt(lhs, rhs) => (eval(lhs) < eval(rhs)) as u32,
+            Lte(lhs, rhs) => (eval(lhs) <= eval(rhs)) as u32,
+            And(lhs, rhs) => eval(lhs) & eval(rhs),
+            Or(lhs, rhs) => eval(lhs) | eval(rhs),
+            Equal(lhs, rhs) => (eval(lhs) == eval(rhs)) as u32,
+            NotEqual(lhs, rhs) => (eval(lhs) != eval(rhs)) as u32,
+            Not(expr) => (eval(expr) == 0) as u32,
+            Add(lhs, rhs) => eval(lhs).wrapping_add(eval(rhs)),
+            Sub(lhs, rhs) => eval(lhs).saturating_sub(eval(rhs)),
+            Mul(lhs, rhs) => eval(lhs).wrapping_mul(eval(rhs)),
+            Div(lhs, rhs) => eval(lhs).checked_div(eval(rhs)).unwrap_or(0),
+            Mod(lhs, rhs) => eval(lhs).checked_rem(eval(rhs)).unwrap_or(0),
+            Min(lhs, rhs) => eval(lhs).min(eval(rhs)),
+            Max(lhs, rhs) => eval(lhs).max(eval(rhs)),
+            Select(condition, if_true, if_false) => {
+                if eval(condition) != 0 {
+                    eval(if_true)
+                } else {
+                    eval(if_false)
+                }
+            }
+        }
+    }
+
+    fn fmt_binary(&self, f: &mut fmt::Formatter, op: &str, lhs: &Expr, rhs: &Expr) -> fmt::Result {
+        let precedence = self.precedence();
+        if lhs.precedence() < precedence {
+            write!(f, "({})", lhs)?;
+        } else {
+            write!(f, "{}", lhs)?;
+        }
+        write!(f, " {} ", op)?;
+        if rhs.precedence() <= precedence {
+            write!(f, "({})", rhs)
+        } else {
+            write!(f, "{}", rhs)
+        }
+    }
+}
+
+/**
+ * Prints an expression in the textual DSL accepted by `automata_dsl_parser::parse_expr`.
+ */
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            U32(val) => write!(f, "{}", val),
+            State => write!(f, "state"),
+            Alive => write!(f, "alive"),
+            Neighbors => write!(f, "n"),
+            Gt(lhs, rhs) => self.fmt_binary(f, ">", lhs, rhs),
+            Gte(lhs, rhs) => self.fmt_binary(f, ">=", lhs, rhs),
+            Lt(lhs, rhs) => self.fmt_binary(f, "<", lhs, rhs),
+            Lte(lhs, rhs) => self.fmt_binary(f, "<=", lhs, rhs),
+            And(lhs, rhs) => self.fmt_binary(f, "&&", lhs, rhs),
+            Or(lhs, rhs) => self.fmt_binary(f, "||", lhs, rhs),
+            Equal(lhs, rhs) => self.fmt_binary(f, "==", lhs, rhs),
+            NotEqual(lhs, rhs) => self.fmt_binary(f, "!=", lhs, rhs),
+            Not(expr) => {
+                if expr.precedence() < self.precedence() {
+                    write!(f, "!({})", expr)
+                } else {
+                    write!(f, "!{}", expr)
+                }
+            }
+            Add(lhs, rhs) => self.fmt_binary(f, "+", lhs, rhs),
+            Sub(lhs, rhs) => self.fmt_binary(f, "-", lhs, rhs),
+            Mul(lhs, rhs) => self.fmt_binary(f, "*", lhs, rhs),
+            Div(lhs, rhs) => self.fmt_binary(f, "/", lhs, rhs),
+            Mod(lhs, rhs) => self.fmt_binary(f, "%", lhs, rhs),
+            Min(lhs, rhs) => write!(f, "min({}, {})", lhs, rhs),
+            Max(lhs, rhs) => write!(f, "max({}, {})", lhs, rhs),
+            Select(condition, if_true, if_false) => {
+                write!(f, "select({}, {}, {})", condition, if_true, if_false)
+            }
         }
     }
 }
@@ -66,7 +162,7 @@ impl Expr {
  * conditionally branch on expressions or set whether the current cell is alive or dead to the
  * result of an expression. Through statements we can describe complex rules to form cellular automata.
  */
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Statement {
     Void,
     SetResult(Expr),
@@ -81,27 +177,210 @@ use Statement::*;
 
 impl Statement {
     /**
-     * Turn a statement into a valid wgsl statement that can be injected into our placeholder
-     * compute shader and executed on the GPU.
+     * Run the statement on the CPU for a two state cell with the given alive flag and neighbor
+     * count and return the new state of the cell. Like the compute shader, the result starts at 0
+     * and a branch that never sets it leaves the cell dead.
+     */
+    pub fn eval(&self, alive: bool, neighbors: u32) -> u32 {
+        self.eval_with_state(alive as u32, alive, neighbors)
+    }
+
+    /**
+     * Run the statement on the CPU for a cell in an arbitrary state. The result is not clamped to
+     * the number of states, see `Rule::eval` for that.
      */
-    pub fn to_shader(&self) -> String {
+    pub fn eval_with_state(&self, state: u32, alive: bool, neighbors: u32) -> u32 {
+        let mut result = 0;
+        self.eval_into(state, alive, neighbors, &mut result);
+        result
+    }
+
+    fn eval_into(&self, state: u32, alive: bool, neighbors: u32, result: &mut u32) {
+        match self {
+            Void => {}
+            SetResult(expr) => *result = expr.eval_with_state(state, alive, neighbors),
+            IfThenElse {
+                condition,
+                if_true_then,
+                if_false_then,
+            } => {
+                if condition.eval_with_state(state, alive, neighbors) != 0 {
+                    if_true_then.eval_into(state, alive, neighbors, result)
+                } else {
+                    if_false_then.eval_into(state, alive, neighbors, result)
+                }
+            }
+        }
+    }
+
+    /**
+     * Every constant the statement may assign to the result. Used to reject rules that would write
+     * a state that does not exist.
+     */
+    fn constant_results(&self, results: &mut Vec<u32>) {
+        match self {
+            Void => {}
+            SetResult(U32(val)) => results.push(*val),
+            SetResult(_) => {}
+            IfThenElse {
+                if_true_then,
+                if_false_then,
+                ..
+            } => {
+                if_true_then.constant_results(results);
+                if_false_then.constant_results(results);
+            }
+        }
+    }
+}
+
+/**
+ * Prints a statement in the textual DSL accepted by `automata_dsl_parser::parse_statement`. An
+ * `IfThenElse` whose false branch is `Void` is printed without an `else`, and nested `IfThenElse`
+ * false branches are printed as `else if` chains.
+ */
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Void => format!(""),
-            SetResult(expr) => format!("result = {};", expr.to_shader()),
+            Void => Ok(()),
+            SetResult(expr) => write!(f, "result = {}", expr),
             IfThenElse {
                 condition,
                 if_true_then,
                 if_false_then,
-            } => format!(
-                "if ({}) {{ {} }} else {{ {} }}",
-                condition.to_shader(),
-                if_true_then.to_shader(),
-                if_false_then.to_shader()
-            ),
+            } => {
+                match if_true_then.as_ref() {
+                    Void => write!(f, "if {} {{ }}", condition)?,
+                    _ => write!(f, "if {} {{ {} }}", condition, if_true_then)?,
+                }
+                match if_false_then.as_ref() {
+                    Void => Ok(()),
+                    IfThenElse { .. } => write!(f, " else {}", if_false_then),
+                    _ => write!(f, " else {{ {} }}", if_false_then),
+                }
+            }
         }
     }
 }
 
+/**
+ * A complete cellular automata rule: the statement every cell runs each generation along with the
+ * number of states a cell can be in and the neighborhood it looks at. States run from 0 (dead) to
+ * `states - 1`. Only neighbors in `alive_state` are counted by `Neighbors`, and `Alive` is true
+ * when the cell itself is in it. Results larger than the last state are clamped to it. Rules
+ * parsed from a rule string remember it in canonical form as their `notation`.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub statement: Statement,
+    pub states: u32,
+    pub alive_state: u32,
+    pub neighborhood: Neighborhood,
+    pub notation: Option<String>,
+}
+
+impl Rule {
+    /**
+     * A rule with the given number of states where state 1 is alive, counting neighbors in the
+     * 3D Moore neighborhood.
+     */
+    pub fn new(statement: Statement, states: u32) -> Self {
+        Self {
+            statement,
+            states,
+            alive_state: 1,
+            neighborhood: Neighborhood::Moore3D,
+            notation: None,
+        }
+    }
+
+    /**
+     * The same rule counting neighbors in `neighborhood`. The notation is dropped if the
+     * neighborhood changes, as it no longer describes the rule.
+     */
+    pub fn with_neighborhood(self, neighborhood: Neighborhood) -> Self {
+        let notation = if neighborhood == self.neighborhood {
+            self.notation
+        } else {
+            None
+        };
+        Self {
+            neighborhood,
+            notation,
+            ..self
+        }
+    }
+
+    pub fn max_state(&self) -> u32 {
+        self.states.saturating_sub(1)
+    }
+
+    /**
+     * Check that the statement type checks, that the state declarations make sense and that the
+     * statement never assigns a constant state beyond the last one.
+     */
+    pub fn validate(&self) -> Result<(), RuleError> {
+        if self.states < 2 || self.alive_state == 0 || self.alive_state >= self.states {
+            return Err(RuleError::InvalidStates {
+                states: self.states,
+                alive_state: self.alive_state,
+            });
+        }
+
+        self.statement.type_check().map_err(RuleError::Type)?;
+
+        let mut results = Vec::new();
+        self.statement.constant_results(&mut results);
+        match results.into_iter().find(|state| *state > self.max_state()) {
+            Some(state) => Err(RuleError::ResultOutOfRange {
+                state,
+                max_state: self.max_state(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /**
+     * The next state of a cell in `state` with `neighbors` alive neighbors, computed on the CPU
+     * exactly as the compute shader does.
+     */
+    pub fn eval(&self, state: u32, neighbors: u32) -> u32 {
+        let alive = state == self.alive_state;
+        self.statement
+            .eval_with_state(state, alive, neighbors)
+            .min(self.max_state())
+    }
+
+    /**
+     * The number of entries in each row of the transition table, one for every possible count of
+     * alive neighbors from zero up to the size of the neighborhood.
+     */
+    pub fn transition_row_length(&self) -> u32 {
+        self.neighborhood.max_neighbors() + 1
+    }
+
+    /**
+     * The rule compiled to a transition table with a row for every state. The next state of a cell
+     * in `state` with `neighbors` alive neighbors is entry
+     * `state * transition_row_length() + neighbors`, exactly what `eval` returns.
+     */
+    pub fn transition_table(&self) -> Vec<u32> {
+        let row_length = self.transition_row_length();
+        (0..self.states)
+            .flat_map(|state| (0..row_length).map(move |neighbors| self.eval(state, neighbors)))
+            .collect()
+    }
+}
+
+/**
+ * A plain statement describes a two state rule.
+ */
+impl From<Statement> for Rule {
+    fn from(statement: Statement) -> Self {
+        Rule::new(statement, 2)
+    }
+}
+
 pub mod exprs {
     use super::Expr;
     use super::Expr::*;
@@ -110,6 +389,10 @@ pub mod exprs {
         U32(value)
     }
 
+    pub fn state() -> Expr {
+        State
+    }
+
     pub fn alive() -> Expr {
         Alive
     }
@@ -145,6 +428,46 @@ pub mod exprs {
     pub fn equal(lhs: Expr, rhs: Expr) -> Expr {
         Equal(Box::new(lhs), Box::new(rhs))
     }
+
+    pub fn not_equal(lhs: Expr, rhs: Expr) -> Expr {
+        NotEqual(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn not(expr: Expr) -> Expr {
+        Not(Box::new(expr))
+    }
+
+    pub fn add(lhs: Expr, rhs: Expr) -> Expr {
+        Add(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn sub(lhs: Expr, rhs: Expr) -> Expr {
+        Sub(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn mul(lhs: Expr, rhs: Expr) -> Expr {
+        Mul(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn div(lhs: Expr, rhs: Expr) -> Expr {
+        Div(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn modulo(lhs: Expr, rhs: Expr) -> Expr {
+        Mod(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn min(lhs: Expr, rhs: Expr) -> Expr {
+        Min(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn max(lhs: Expr, rhs: Expr) -> Expr {
+        Max(Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn select(condition: Expr, if_true: Expr, if_false: Expr) -> Expr {
+        Select(Box::new(condition), Box::new(if_true), Box::new(if_false))
+    }
 }
 
 pub mod statements {
@@ -173,7 +496,7 @@ pub mod statements {
 }
 
 pub mod rulesets {
-    use super::{exprs::*, statements::*, Statement};
+    use super::{exprs::*, statements::*, Expr, Rule, Statement};
 
     /**
      * An implementation of conways game of life in
@@ -189,4 +512,167 @@ pub mod rulesets {
             set_result(equal(neighbors(), const_u32(3))),
         )
     }
+
+    /**
+     * True when the neighbor count is one of `counts`, or `None` if there are no counts. Runs of
+     * three or more consecutive counts are tested as a range.
+     */
+    fn neighbors_in(counts: &[u32]) -> Option<Expr> {
+        let mut counts = counts.to_vec();
+        counts.sort_unstable();
+        counts.dedup();
+
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        for count in counts {
+            match runs.last_mut() {
+                Some((_, end)) if *end + 1 == count => *end = count,
+                _ => runs.push((count, count)),
+            }
+        }
+
+        runs.into_iter()
+            .flat_map(|(start, end)| {
+                if end - start >= 2 {
+                    vec![and(
+                        gte(neighbors(), const_u32(start)),
+                        lte(neighbors(), const_u32(end)),
+                    )]
+                } else {
+                    (start..=end)
+                        .map(|count| equal(neighbors(), const_u32(count)))
+                        .collect()
+                }
+            })
+            .reduce(or)
+    }
+
+    /**
+     * A Generations style rule. Dead cells (state 0) with a neighbor count in `birth` become alive
+     * (state 1). Alive cells with a neighbor count in `survive` stay alive, otherwise they start
+     * to decay through states 2, 3, .. `states - 1` one step per generation before dying. With two
+     * states this is an ordinary life-like rule.
+     */
+    pub fn generations(survive: &[u32], birth: &[u32], states: u32) -> Rule {
+        let decayed = if states > 2 { 2 } else { 0 };
+
+        let born = match neighbors_in(birth) {
+            Some(condition) => set_result(condition),
+            None => set_result(const_u32(0)),
+        };
+
+        let survived = match neighbors_in(survive) {
+            Some(condition) if decayed == 0 => set_result(condition),
+            Some(condition) => set_result(select(condition, const_u32(1), const_u32(decayed))),
+            None => set_result(const_u32(decayed)),
+        };
+
+        let statement = if states > 2 {
+            if_then_else(
+                equal(state(), const_u32(0)),
+                born,
+                if_then_else(
+                    alive(),
+                    survived,
+                    set_result(modulo(add(state(), const_u32(1)), const_u32(states))),
+                ),
+            )
+        } else {
+            if_then_else(alive(), survived, born)
+        };
+
+        Rule::new(statement, states)
+    }
+
+    /**
+     * Brian's Brain: cells are born with exactly two neighbors, and always decay after one
+     * generation alive.
+     */
+    pub fn brians_brain() -> Rule {
+        generations(&[], &[2], 3)
+    }
+
+    /**
+     * Star Wars, a four state Generations rule (345/2/4).
+     */
+    pub fn star_wars() -> Rule {
+        generations(&[3, 4, 5], &[2], 4)
+    }
+
+    /**
+     * The 3D "445" rule: survival and birth on four of the 26 neighbors, with five states.
+     */
+    pub fn four_four_five() -> Rule {
+        generations(&[4], &[4], 5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> u32 {
+        source.parse::<Expr>().unwrap().eval(false, 0)
+    }
+
+    #[test]
+    fn division_and_remainder_by_zero_are_zero() {
+        assert_eq!(eval("7 / 0"), 0);
+        assert_eq!(eval("7 % 0"), 0);
+        assert_eq!(eval("7 / 2"), 3);
+        assert_eq!(eval("7 % 2"), 1);
+    }
+
+    #[test]
+    fn subtraction_saturates_and_addition_wraps() {
+        assert_eq!(eval("2 - 5"), 0);
+        assert_eq!(eval("5 - 2"), 3);
+        assert_eq!(eval("4294967295 + 2"), 1);
+        assert_eq!(eval("2147483648 * 2"), 0);
+    }
+
+    #[test]
+    fn booleans_are_zero_or_one() {
+        assert_eq!(eval("3 > 2"), 1);
+        assert_eq!(eval("!(3 > 2)"), 0);
+        assert_eq!(eval("select(2 == 2, 5, 6)"), 5);
+        assert_eq!("alive".parse::<Expr>().unwrap().eval(true, 0), 1);
+        assert_eq!("n".parse::<Expr>().unwrap().eval(false, 6), 6);
+    }
+
+    #[test]
+    fn unset_result_is_dead() {
+        let statement: Statement = "if n == 3 { result = 1 }".parse().unwrap();
+        assert_eq!(statement.eval(false, 3), 1);
+        assert_eq!(statement.eval(true, 2), 0);
+    }
+
+    #[test]
+    fn rule_eval_clamps_to_the_last_state() {
+        let rule = Rule::new("result = state + 1".parse().unwrap(), 3);
+        assert_eq!(rule.eval(0, 0), 1);
+        assert_eq!(rule.eval(1, 0), 2);
+        assert_eq!(rule.eval(2, 0), 2);
+    }
+
+    #[test]
+    fn transition_table_matches_eval() {
+        for rule in [
+            rulesets::star_wars().with_neighborhood(Neighborhood::Moore2D),
+            rulesets::generations(&[1, 2], &[1], 3).with_neighborhood(Neighborhood::VonNeumann2D),
+            rulesets::four_four_five().with_neighborhood(Neighborhood::VonNeumann3D),
+        ] {
+            let table = rule.transition_table();
+            let row_length = rule.neighborhood.max_neighbors() + 1;
+            assert_eq!(rule.transition_row_length(), row_length);
+            assert_eq!(table.len() as u32, rule.states * row_length);
+            for state in 0..rule.states {
+                for neighbors in 0..row_length {
+                    assert_eq!(
+                        table[(state * row_length + neighbors) as usize],
+                        rule.eval(state, neighbors)
+                    );
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,579 @@
+use crate::automata_dsl::{exprs, statements, Expr, Statement};
+use std::fmt;
+use std::str::FromStr;
+
+/**
+ * An error produced while lexing or parsing the textual form of the automata DSL. The line and
+ * column are 1-based and point at the first character of the offending token.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(u32),
+    Ident(String),
+    LParen,
+    RParen,
+    Comma,
+    LBrace,
+    RBrace,
+    Assign,
+    Semicolon,
+    AndAnd,
+    OrOr,
+    EqEq,
+    NotEq,
+    Bang,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "number `{}`", value),
+            Token::Ident(name) => write!(f, "`{}`", name),
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::LBrace => write!(f, "`{{`"),
+            Token::RBrace => write!(f, "`}}`"),
+            Token::Assign => write!(f, "`=`"),
+            Token::Semicolon => write!(f, "`;`"),
+            Token::AndAnd => write!(f, "`&&`"),
+            Token::OrOr => write!(f, "`||`"),
+            Token::EqEq => write!(f, "`==`"),
+            Token::NotEq => write!(f, "`!=`"),
+            Token::Bang => write!(f, "`!`"),
+            Token::Plus => write!(f, "`+`"),
+            Token::Minus => write!(f, "`-`"),
+            Token::Star => write!(f, "`*`"),
+            Token::Slash => write!(f, "`/`"),
+            Token::Percent => write!(f, "`%`"),
+            Token::Lt => write!(f, "`<`"),
+            Token::Lte => write!(f, "`<=`"),
+            Token::Gt => write!(f, "`>`"),
+            Token::Gte => write!(f, "`>=`"),
+            Token::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Spanned {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+/**
+ * Split the source into tokens, tracking the line and column each token starts at. Whitespace and
+ * `//` or `#` line comments are skipped.
+ */
+fn lex(source: &str) -> Result<Vec<Spanned>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut column = 1;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let (start_line, start_column) = (line, column);
+        let error = |message: String| ParseError {
+            line: start_line,
+            column: start_column,
+            message,
+        };
+
+        if c == '\n' {
+            i += 1;
+            line += 1;
+            column = 1;
+            continue;
+        }
+
+        if c.is_whitespace() {
+            i += 1;
+            column += 1;
+            continue;
+        }
+
+        if c == '#' || (c == '/' && chars.get(i + 1) == Some(&'/')) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        let next = chars.get(i + 1).copied();
+
+        let (token, len) = if c.is_ascii_digit() {
+            let len = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+            let digits: String = chars[i..i + len].iter().collect();
+            let value = digits.parse::<u32>().map_err(|_| {
+                error(format!(
+                    "integer literal `{}` does not fit in a u32",
+                    digits
+                ))
+            })?;
+            (Token::Number(value), len)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = chars[i..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .count();
+            (Token::Ident(chars[i..i + len].iter().collect()), len)
+        } else {
+            match (c, next) {
+                ('&', Some('&')) => (Token::AndAnd, 2),
+                ('|', Some('|')) => (Token::OrOr, 2),
+                ('=', Some('=')) => (Token::EqEq, 2),
+                ('!', Some('=')) => (Token::NotEq, 2),
+                ('<', Some('=')) => (Token::Lte, 2),
+                ('>', Some('=')) => (Token::Gte, 2),
+                ('<', _) => (Token::Lt, 1),
+                ('>', _) => (Token::Gt, 1),
+                ('=', _) => (Token::Assign, 1),
+                ('!', _) => (Token::Bang, 1),
+                ('+', _) => (Token::Plus, 1),
+                ('-', _) => (Token::Minus, 1),
+                ('*', _) => (Token::Star, 1),
+                ('/', _) => (Token::Slash, 1),
+                ('%', _) => (Token::Percent, 1),
+                (',', _) => (Token::Comma, 1),
+                ('(', _) => (Token::LParen, 1),
+                (')', _) => (Token::RParen, 1),
+                ('{', _) => (Token::LBrace, 1),
+                ('}', _) => (Token::RBrace, 1),
+                (';', _) => (Token::Semicolon, 1),
+                _ => return Err(error(format!("unexpected character `{}`", c))),
+            }
+        };
+
+        tokens.push(Spanned {
+            token,
+            line: start_line,
+            column: start_column,
+        });
+        i += len;
+        column += len;
+    }
+
+    tokens.push(Spanned {
+        token: Token::Eof,
+        line,
+        column,
+    });
+
+    Ok(tokens)
+}
+
+/**
+ * A recursive descent parser over the token stream. Binary operators are left associative and
+ * bind, from loosest to tightest: `||`, `&&`, `==` / `!=`, the ordering comparisons, `+` / `-`,
+ * then `*` / `/` / `%`. Prefix `!` (or `not`) binds tighter than any binary operator.
+ */
+struct Parser {
+    tokens: Vec<Spanned>,
+    position: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Self, ParseError> {
+        Ok(Self {
+            tokens: lex(source)?,
+            position: 0,
+        })
+    }
+
+    fn peek(&self) -> &Spanned {
+        &self.tokens[self.position]
+    }
+
+    fn advance(&mut self) -> Spanned {
+        let token = self.tokens[self.position].clone();
+        if token.token != Token::Eof {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error_at(token: &Spanned, message: String) -> ParseError {
+        ParseError {
+            line: token.line,
+            column: token.column,
+            message,
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.peek().token, Token::Ident(name) if name == keyword)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        let token = self.advance();
+        if token.token == expected {
+            Ok(())
+        } else {
+            Err(Self::error_at(
+                &token,
+                format!("expected {} but found {}", expected, token.token),
+            ))
+        }
+    }
+
+    fn expect_eof(&mut self) -> Result<(), ParseError> {
+        let token = self.peek();
+        if token.token == Token::Eof {
+            Ok(())
+        } else {
+            Err(Self::error_at(
+                token,
+                format!("expected end of input but found {}", token.token),
+            ))
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        if self.is_keyword("if") {
+            self.advance();
+            let condition = self.expr()?;
+            let if_true_then = self.block()?;
+            let if_false_then = if self.is_keyword("else") {
+                self.advance();
+                if self.is_keyword("if") {
+                    self.statement()?
+                } else {
+                    self.block()?
+                }
+            } else {
+                statements::void()
+            };
+            Ok(statements::if_then_else(
+                condition,
+                if_true_then,
+                if_false_then,
+            ))
+        } else if self.is_keyword("result") {
+            self.advance();
+            self.expect(Token::Assign)?;
+            let expr = self.expr()?;
+            if self.peek().token == Token::Semicolon {
+                self.advance();
+            }
+            Ok(statements::set_result(expr))
+        } else {
+            match self.peek().token {
+                Token::RBrace | Token::Eof => Ok(statements::void()),
+                _ => {
+                    let token = self.peek();
+                    Err(Self::error_at(
+                        token,
+                        format!("expected `if` or `result` but found {}", token.token),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn block(&mut self) -> Result<Statement, ParseError> {
+        self.expect(Token::LBrace)?;
+        let statement = self.statement()?;
+        self.expect(Token::RBrace)?;
+        Ok(statement)
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.or()
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.and()?;
+        while self.peek().token == Token::OrOr {
+            self.advance();
+            lhs = exprs::or(lhs, self.and()?);
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.equality()?;
+        while self.peek().token == Token::AndAnd {
+            self.advance();
+            lhs = exprs::and(lhs, self.equality()?);
+        }
+        Ok(lhs)
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.comparison()?;
+        loop {
+            let op = match self.peek().token {
+                Token::EqEq => exprs::equal,
+                Token::NotEq => exprs::not_equal,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = op(lhs, self.comparison()?);
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.additive()?;
+        loop {
+            let op = match self.peek().token {
+                Token::Lt => exprs::lt,
+                Token::Lte => exprs::lte,
+                Token::Gt => exprs::gt,
+                Token::Gte => exprs::gte,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = op(lhs, self.additive()?);
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.multiplicative()?;
+        loop {
+            let op = match self.peek().token {
+                Token::Plus => exprs::add,
+                Token::Minus => exprs::sub,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = op(lhs, self.multiplicative()?);
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek().token {
+                Token::Star => exprs::mul,
+                Token::Slash => exprs::div,
+                Token::Percent => exprs::modulo,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            lhs = op(lhs, self.unary()?);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek().token == Token::Bang || self.is_keyword("not") {
+            self.advance();
+            Ok(exprs::not(self.unary()?))
+        } else {
+            self.primary()
+        }
+    }
+
+    /**
+     * Parse the parenthesised, comma separated arguments of a call to a builtin function.
+     */
+    fn arguments(&mut self, name: &Spanned, count: usize) -> Result<Vec<Expr>, ParseError> {
+        self.expect(Token::LParen)?;
+        let mut arguments = vec![self.expr()?];
+        while self.peek().token == Token::Comma {
+            self.advance();
+            arguments.push(self.expr()?);
+        }
+        self.expect(Token::RParen)?;
+
+        if arguments.len() == count {
+            Ok(arguments)
+        } else {
+            Err(Self::error_at(
+                name,
+                format!(
+                    "{} expects {} arguments but was given {}",
+                    name.token,
+                    count,
+                    arguments.len()
+                ),
+            ))
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.advance();
+        match &token.token {
+            Token::Number(value) => Ok(exprs::const_u32(*value)),
+            Token::Ident(name) => match name.as_str() {
+                "state" => Ok(exprs::state()),
+                "alive" => Ok(exprs::alive()),
+                "n" | "neighbors" => Ok(exprs::neighbors()),
+                "min" | "max" => {
+                    let mut arguments = self.arguments(&token, 2)?.into_iter();
+                    let (lhs, rhs) = (arguments.next().unwrap(), arguments.next().unwrap());
+                    if name == "min" {
+                        Ok(exprs::min(lhs, rhs))
+                    } else {
+                        Ok(exprs::max(lhs, rhs))
+                    }
+                }
+                "select" => {
+                    let mut arguments = self.arguments(&token, 3)?.into_iter();
+                    Ok(exprs::select(
+                        arguments.next().unwrap(),
+                        arguments.next().unwrap(),
+                        arguments.next().unwrap(),
+                    ))
+                }
+                _ => Err(Self::error_at(
+                    &token,
+                    format!("unknown identifier `{}`", name),
+                )),
+            },
+            Token::LParen => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            other => Err(Self::error_at(
+                &token,
+                format!("expected an expression but found {}", other),
+            )),
+        }
+    }
+}
+
+/**
+ * Parse a complete rule, e.g. `if alive { result = n == 2 || n == 3 } else { result = n == 3 }`.
+ * An empty source (or one containing only comments) parses to `Statement::Void`.
+ */
+pub fn parse_statement(source: &str) -> Result<Statement, ParseError> {
+    let mut parser = Parser::new(source)?;
+    let statement = parser.statement()?;
+    parser.expect_eof()?;
+    Ok(statement)
+}
+
+/**
+ * Parse a single expression, e.g. `n >= 2 && n <= 3`.
+ */
+pub fn parse_expr(source: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser::new(source)?;
+    let expr = parser.expr()?;
+    parser.expect_eof()?;
+    Ok(expr)
+}
+
+impl FromStr for Statement {
+    type Err = ParseError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        parse_statement(source)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        parse_expr(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata_dsl::exprs::*;
+    use crate::automata_dsl::rulesets;
+
+    fn round_trip(source: &str) {
+        let statement = parse_statement(source).unwrap();
+        let printed = statement.to_string();
+        assert_eq!(parse_statement(&printed).unwrap(), statement, "{}", printed);
+    }
+
+    #[test]
+    fn parses_game_of_life() {
+        let statement = parse_statement(
+            "# Conway's game of life
+            if alive {
+              result = n == 2 || n == 3
+            } else {
+              result = n == 3
+            }",
+        )
+        .unwrap();
+        for neighbors in 0..=8 {
+            for alive in [false, true] {
+                assert_eq!(
+                    statement.eval(alive, neighbors),
+                    rulesets::conways_game_of_life().eval(alive, neighbors)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(
+            parse_expr("1 + 2 * 3").unwrap(),
+            add(const_u32(1), mul(const_u32(2), const_u32(3)))
+        );
+        assert_eq!(
+            parse_expr("8 - 4 - 2").unwrap(),
+            sub(sub(const_u32(8), const_u32(4)), const_u32(2))
+        );
+        assert_eq!(
+            parse_expr("alive || n > 1 && n < 4").unwrap(),
+            or(
+                alive(),
+                and(gt(neighbors(), const_u32(1)), lt(neighbors(), const_u32(4)))
+            )
+        );
+    }
+
+    #[test]
+    fn printer_round_trips() {
+        round_trip("if alive { result = n == 2 || n == 3 } else { result = n == 3 }");
+        round_trip("result = (n + 1) * 2 - state / (3 % 2)");
+        round_trip("result = 8 - (4 - 2)");
+        round_trip("result = !(alive && n > 3) || min(n, 2) >= max(state, 1)");
+        round_trip("result = select(n == 2, state + 1, 0)");
+        round_trip("if n < 2 { result = 0 } else if n > 3 { result = 0 } else { result = 1 }");
+        round_trip("if alive { }");
+        round_trip("");
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_token() {
+        let err = parse_statement("result = n ==").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 14));
+
+        let err = parse_statement("if alive {\n  result = n @ 2\n}").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 14));
+
+        let err = parse_statement("if alive {\n  result = 1\n").unwrap_err();
+        assert_eq!(err.line, 3);
+
+        let err = parse_expr("n == 99999999999").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 6));
+    }
+}
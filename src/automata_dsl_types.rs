@@ -0,0 +1,231 @@
+use crate::automata_dsl::{Expr, Expr::*, Statement, Statement::*};
+use std::fmt;
+
+/**
+ * The type of a DSL expression. Both are evaluated as u32s, with booleans as 0 or 1.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    U32,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "bool"),
+            Type::U32 => write!(f, "u32"),
+        }
+    }
+}
+
+/**
+ * An expression that mixes types in a way the DSL does not allow. `expr` is the smallest
+ * sub-expression that is ill-typed.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub expr: Expr,
+    pub message: String,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in `{}`", self.message, self.expr)
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+/**
+ * A reason a `Rule` cannot be run.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    Type(TypeError),
+    InvalidStates { states: u32, alive_state: u32 },
+    ResultOutOfRange { state: u32, max_state: u32 },
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleError::Type(err) => write!(f, "{}", err),
+            RuleError::InvalidStates {
+                states,
+                alive_state,
+            } => write!(
+                f,
+                "a rule needs at least 2 states and an alive state between 1 and {}, \
+                 but has {} states with alive state {}",
+                states.saturating_sub(1),
+                states,
+                alive_state
+            ),
+            RuleError::ResultOutOfRange { state, max_state } => write!(
+                f,
+                "the rule sets the result to state {} but the last state is {}",
+                state, max_state
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+impl Expr {
+    /**
+     * Infer the type of an expression. Arithmetic, `Min` and `Max` take two u32s and produce a u32,
+     * comparisons take two u32s and produce a bool, `Equal` / `NotEqual` take two operands of the
+     * same type and produce a bool, `And` / `Or` take two operands of the same type and produce
+     * that type (logical on bools, bitwise on u32s), `Not` takes a bool, and `Select` takes a bool
+     * condition and two branches of the same type. Anything else is rejected with a description of
+     * the offending sub-expression.
+     */
+    pub fn type_of(&self) -> Result<Type, TypeError> {
+        let error = |message: String| TypeError {
+            expr: self.clone(),
+            message,
+        };
+
+        let expect = |operand: &Expr, expected: Type, what: &str| {
+            let operand_type = operand.type_of()?;
+            if operand_type == expected {
+                Ok(())
+            } else {
+                Err(error(format!(
+                    "{} expects {} but `{}` is {}",
+                    what, expected, operand, operand_type
+                )))
+            }
+        };
+
+        let same_type = |lhs: &Expr, rhs: &Expr| {
+            let (lhs_type, rhs_type) = (lhs.type_of()?, rhs.type_of()?);
+            if lhs_type == rhs_type {
+                Ok(lhs_type)
+            } else {
+                Err(error(format!(
+                    "operands have different types: `{}` is {} but `{}` is {}",
+                    lhs, lhs_type, rhs, rhs_type
+                )))
+            }
+        };
+
+        match self {
+            U32(_) | State | Neighbors => Ok(Type::U32),
+            Alive => Ok(Type::Bool),
+            Gt(lhs, rhs) | Gte(lhs, rhs) | Lt(lhs, rhs) | Lte(lhs, rhs) => {
+                expect(lhs, Type::U32, "comparison")?;
+                expect(rhs, Type::U32, "comparison")?;
+                Ok(Type::Bool)
+            }
+            Add(lhs, rhs)
+            | Sub(lhs, rhs)
+            | Mul(lhs, rhs)
+            | Div(lhs, rhs)
+            | Mod(lhs, rhs)
+            | Min(lhs, rhs)
+            | Max(lhs, rhs) => {
+                expect(lhs, Type::U32, "arithmetic")?;
+                expect(rhs, Type::U32, "arithmetic")?;
+                Ok(Type::U32)
+            }
+            Equal(lhs, rhs) | NotEqual(lhs, rhs) => same_type(lhs, rhs).map(|_| Type::Bool),
+            And(lhs, rhs) | Or(lhs, rhs) => same_type(lhs, rhs),
+            Not(expr) => {
+                expect(expr, Type::Bool, "`!`")?;
+                Ok(Type::Bool)
+            }
+            Select(condition, if_true, if_false) => {
+                expect(condition, Type::Bool, "select condition")?;
+                same_type(if_true, if_false)
+            }
+        }
+    }
+}
+
+impl Statement {
+    /**
+     * Check that every expression in the statement is well typed. Statements accept either type:
+     * a bool assigned to the result is converted to 0 or 1 and a u32 condition is true when it is
+     * non-zero, so only mixing types inside an expression is an error.
+     */
+    pub fn type_check(&self) -> Result<(), TypeError> {
+        match self {
+            Void => Ok(()),
+            SetResult(expr) => expr.type_of().map(|_| ()),
+            IfThenElse {
+                condition,
+                if_true_then,
+                if_false_then,
+            } => {
+                condition.type_of()?;
+                if_true_then.type_check()?;
+                if_false_then.type_check()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata_dsl::{exprs::*, rulesets, statements::*, Rule};
+
+    fn type_of(source: &str) -> Result<Type, TypeError> {
+        source.parse::<Expr>().unwrap().type_of()
+    }
+
+    #[test]
+    fn names_the_ill_typed_sub_expression() {
+        let ill_typed = and(alive(), const_u32(1));
+        let statement = set_result(or(equal(neighbors(), const_u32(3)), ill_typed.clone()));
+        let err = statement.type_check().unwrap_err();
+        assert_eq!(err.expr, ill_typed);
+        assert!(err.to_string().ends_with("in `alive && 1`"), "{}", err);
+
+        // Conditions are checked too
+        let statement = if_then_else(ill_typed.clone(), void(), void());
+        assert_eq!(statement.type_check().unwrap_err().expr, ill_typed);
+    }
+
+    #[test]
+    fn rejects_mixed_types() {
+        for source in [
+            "!n",
+            "select(n, 1, 2)",
+            "select(alive, 1, alive)",
+            "alive + 1",
+            "n > alive",
+            "alive == 1",
+            "min(alive, n)",
+        ] {
+            assert!(type_of(source).is_err(), "{} should be rejected", source);
+        }
+    }
+
+    #[test]
+    fn accepts_well_typed_expressions() {
+        assert_eq!(type_of("alive == (n > 2)"), Ok(Type::Bool));
+        assert_eq!(type_of("!alive || n == 3"), Ok(Type::Bool));
+        // `&&` and `||` on u32s are bitwise
+        assert_eq!(type_of("n && 1"), Ok(Type::U32));
+        assert_eq!(type_of("select(alive, state + 1, 0)"), Ok(Type::U32));
+        assert_eq!(rulesets::conways_game_of_life().type_check(), Ok(()));
+        // Statements accept either type in conditions and results
+        assert_eq!(
+            if_then_else(neighbors(), set_result(alive()), void()).type_check(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rules_report_type_errors() {
+        let rule = Rule::new(set_result(not(neighbors())), 2);
+        assert!(matches!(
+            rule.validate(),
+            Err(RuleError::Type(TypeError { expr, .. })) if expr == not(neighbors())
+        ));
+    }
+}
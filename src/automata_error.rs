@@ -0,0 +1,169 @@
+use crate::automata_dsl_types::RuleError;
+use crate::neighborhood::Neighborhood;
+use glam::UVec3;
+use std::fmt;
+
+/**
+ * Why an automata, or the device it runs on, could not be created.
+ */
+#[derive(Debug)]
+pub enum AutomataError {
+    /* A generated shader failed validation. `line` is 1-based and `source_line` is that line of
+     * the generated WGSL */
+    Shader {
+        message: String,
+        line: Option<u32>,
+        source_line: Option<String>,
+    },
+    /* No adapter was found, not even a software one */
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    /* The automata needs more of a resource than the device offers */
+    LimitExceeded {
+        limit: &'static str,
+        required: u64,
+        supported: u64,
+    },
+    Rule(RuleError),
+    /* A 2D automata was given a rule that counts neighbors in other z layers */
+    NotPlanar(Neighborhood),
+    /* An initial state with a different number of cells than the grid */
+    StateSizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /* An initial state with a cell past the rule's last state */
+    InvalidState {
+        state: u32,
+        max_state: u32,
+    },
+    /* A grid with no cells, or more cells than a u32 id can address */
+    InvalidDimensions(UVec3),
+    /* A live cell or pattern of `size` cells at `position` that does not fit in the grid */
+    OutOfBounds {
+        position: UVec3,
+        size: UVec3,
+        dim: UVec3,
+    },
+}
+
+impl fmt::Display for AutomataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AutomataError::Shader {
+                message,
+                line,
+                source_line,
+            } => {
+                write!(f, "the generated shader is invalid: {}", message)?;
+                if let (Some(line), Some(source_line)) = (line, source_line) {
+                    write!(f, " (line {}: `{}`)", line, source_line.trim())?;
+                }
+                Ok(())
+            }
+            AutomataError::NoAdapter => write!(f, "could not find an appropriate adapter"),
+            AutomataError::RequestDevice(err) => write!(f, "could not create a device: {}", err),
+            AutomataError::LimitExceeded {
+                limit,
+                required,
+                supported,
+            } => write!(
+                f,
+                "{} of {} exceeds the device limit of {}",
+                limit, required, supported
+            ),
+            AutomataError::Rule(err) => write!(f, "invalid automata rule: {}", err),
+            AutomataError::NotPlanar(neighborhood) => write!(
+                f,
+                "a 2D automata needs a 2D neighborhood but the rule uses {:?}",
+                neighborhood
+            ),
+            AutomataError::StateSizeMismatch { expected, found } => write!(
+                f,
+                "the state has {} cells but the automata has {}",
+                found, expected
+            ),
+            AutomataError::InvalidState { state, max_state } => write!(
+                f,
+                "the state contains {} but the last state of the rule is {}",
+                state, max_state
+            ),
+            AutomataError::InvalidDimensions(dim) => write!(
+                f,
+                "a grid of {} cells must have between 1 and {} cells",
+                dim,
+                u32::MAX
+            ),
+            AutomataError::OutOfBounds {
+                position,
+                size,
+                dim,
+            } => write!(
+                f,
+                "a region of {} cells at {} does not fit in a grid of {}",
+                size, position, dim
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AutomataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AutomataError::RequestDevice(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<RuleError> for AutomataError {
+    fn from(err: RuleError) -> Self {
+        AutomataError::Rule(err)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for AutomataError {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        AutomataError::RequestDevice(err)
+    }
+}
+
+/**
+ * Parse and validate generated WGSL with naga before it reaches the device, where an invalid
+ * shader is a panic rather than an error.
+ */
+pub(crate) fn validate_shader(source: &str) -> Result<(), AutomataError> {
+    let shader_error = |message: String, location: Option<naga::SourceLocation>| {
+        let line = location.map(|location| location.line_number);
+        AutomataError::Shader {
+            message,
+            line,
+            source_line: line
+                .and_then(|line| source.lines().nth(line as usize - 1))
+                .map(str::to_string),
+        }
+    };
+
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|err| shader_error(err.to_string(), err.location(source)))?;
+
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .map_err(|err| {
+        // The outer error only names the function, the cause says what is wrong with it
+        let mut message = err.to_string();
+        let mut cause = std::error::Error::source(err.as_inner());
+        while let Some(err) = cause {
+            message = format!("{}: {}", message, err);
+            cause = err.source();
+        }
+        // The innermost span points at the offending expression rather than the whole function
+        let location = err.spans().last().map(|(span, _)| span.location(source));
+        shader_error(message, location)
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use glam::UVec3;
+
+/**
+ * The number of u32 words in each row of a bit packed grid. Every row starts on a new word, so
+ * the last word of a row is padded with zero bits when `dim.x` is not a multiple of 32.
+ */
+pub fn packed_row_words(dim: &UVec3) -> u32 {
+    dim.x.div_ceil(32)
+}
+
+/**
+ * The number of u32 words a bit packed grid of `dim` cells takes.
+ */
+pub fn packed_words(dim: &UVec3) -> usize {
+    packed_row_words(dim) as usize * dim.y as usize * dim.z as usize
+}
+
+/**
+ * Pack a grid of two state cells in id order into words of 32 cells along x. The cell at `x` is
+ * bit `x % 32` of word `x / 32` of its row. Any non-zero state is packed as a 1.
+ */
+pub fn pack_cells(dim: &UVec3, cells: &[u32]) -> Vec<u32> {
+    let row_words = packed_row_words(dim) as usize;
+    let mut words = vec![0; packed_words(dim)];
+    for (row, cells) in cells.chunks(dim.x as usize).enumerate() {
+        for (x, cell) in cells.iter().enumerate() {
+            if *cell != 0 {
+                words[row * row_words + x / 32] |= 1 << (x % 32);
+            }
+        }
+    }
+    words
+}
+
+/**
+ * The inverse of `pack_cells`.
+ */
+pub fn unpack_cells(dim: &UVec3, words: &[u32]) -> Vec<u32> {
+    let row_words = packed_row_words(dim) as usize;
+    words
+        .chunks(row_words)
+        .flat_map(|row| (0..dim.x as usize).map(move |x| (row[x / 32] >> (x % 32)) & 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_start_on_a_new_word() {
+        let dim = UVec3::new(37, 3, 2);
+        assert_eq!(packed_row_words(&dim), 2);
+        assert_eq!(packed_words(&dim), 12);
+        assert_eq!(packed_row_words(&UVec3::new(64, 1, 1)), 2);
+
+        let mut cells = vec![0; 37 * 3 * 2];
+        cells[0] = 1;
+        cells[36] = 1;
+        cells[37 + 31] = 3;
+        cells[37 * 3 + 32] = 1;
+        let words = pack_cells(&dim, &cells);
+        assert_eq!(words[0], 1);
+        assert_eq!(words[1], 1 << 4);
+        assert_eq!(words[2], 1 << 31);
+        assert_eq!(words[7], 1);
+        assert_eq!(words.iter().map(|word| word.count_ones()).sum::<u32>(), 4);
+    }
+
+    #[test]
+    fn cells_round_trip() {
+        for dim in [
+            UVec3::new(37, 3, 2),
+            UVec3::new(32, 2, 1),
+            UVec3::new(5, 7, 1),
+        ] {
+            let cells: Vec<u32> = (0..dim.x * dim.y * dim.z)
+                .map(|id| (id * 7 % 3 == 0) as u32)
+                .collect();
+            let words = pack_cells(&dim, &cells);
+            assert_eq!(words.len(), packed_words(&dim));
+            assert_eq!(unpack_cells(&dim, &words), cells);
+        }
+    }
+}
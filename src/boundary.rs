@@ -0,0 +1,188 @@
+/**
+ * What a cell sees when its neighborhood reaches past the edge of the grid along one axis.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    /* Cells whose neighborhood reaches past the edge see no neighbors at all */
+    Skip,
+    /* The grid wraps around, so the axis forms a torus */
+    Wrap,
+    /* The grid is mirrored at the edge, so the cell past the edge is the edge cell itself */
+    Reflect,
+    /* Cells past the edge are always dead */
+    Dead,
+    /* Cells past the edge are always alive */
+    Alive,
+}
+
+/**
+ * The boundary of each axis of the grid. The default skips cells at every edge.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Boundaries {
+    pub x: Boundary,
+    pub y: Boundary,
+    pub z: Boundary,
+}
+
+impl Boundaries {
+    pub fn all(boundary: Boundary) -> Self {
+        Self {
+            x: boundary,
+            y: boundary,
+            z: boundary,
+        }
+    }
+
+    /**
+     * Generate the wgsl functions `boundary_x`, `boundary_y` and `boundary_z` used by
+     * `neighbor_at` in the compute shader.
+     */
+    pub fn to_shader(&self) -> String {
+        [
+            self.x.to_shader("boundary_x"),
+            self.y.to_shader("boundary_y"),
+            self.z.to_shader("boundary_z"),
+        ]
+        .join("\n\n")
+    }
+}
+
+impl Default for Boundaries {
+    fn default() -> Self {
+        Self::all(Boundary::Skip)
+    }
+}
+
+/**
+ * Where a coordinate along one axis ends up after applying the boundary.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolved {
+    Cell(u32),
+    Dead,
+    Alive,
+}
+
+/* Sentinel coordinates the generated wgsl uses for constant cells past the edge */
+const SHADER_DEAD: i32 = -1;
+const SHADER_ALIVE: i32 = -2;
+
+impl Boundary {
+    /**
+     * Map a coordinate that may be past the edge of an axis of `size` cells back onto the grid.
+     * `Skip` leaves coordinates alone, callers must not look past a skipped edge.
+     */
+    pub fn resolve(&self, coordinate: i32, size: u32) -> Resolved {
+        let size = size as i32;
+        let inside = coordinate >= 0 && coordinate < size;
+        match self {
+            Boundary::Skip => Resolved::Cell(coordinate as u32),
+            Boundary::Wrap => Resolved::Cell(coordinate.rem_euclid(size) as u32),
+            Boundary::Reflect if coordinate < 0 => {
+                Resolved::Cell((-coordinate - 1).min(size - 1) as u32)
+            }
+            Boundary::Reflect if coordinate >= size => {
+                Resolved::Cell((2 * size - coordinate - 1).max(0) as u32)
+            }
+            Boundary::Reflect => Resolved::Cell(coordinate as u32),
+            _ if inside => Resolved::Cell(coordinate as u32),
+            Boundary::Dead => Resolved::Dead,
+            Boundary::Alive => Resolved::Alive,
+        }
+    }
+
+    /**
+     * Generate a wgsl function named `name` that does the same as `resolve`, returning the
+     * coordinate on the grid, -1 for a dead cell or -2 for an alive cell past the edge.
+     */
+    pub fn to_shader(&self, name: &str) -> String {
+        let body = match self {
+            Boundary::Skip => "return c;".to_string(),
+            // Only take remainders of non-negative values, `%` of a negative i32 is not portable
+            Boundary::Wrap => "if c < 0 {\n    return size - 1 - (-c - 1) % size;\n  }\n  \
+                 return c % size;"
+                .to_string(),
+            Boundary::Reflect => "if c < 0 {\n    return min(-c - 1, size - 1);\n  }\n  \
+                 if c >= size {\n    return max(2 * size - c - 1, 0);\n  }\n  return c;"
+                .to_string(),
+            Boundary::Dead | Boundary::Alive => format!(
+                "if c < 0 || c >= size {{\n    return {};\n  }}\n  return c;",
+                if *self == Boundary::Dead {
+                    SHADER_DEAD
+                } else {
+                    SHADER_ALIVE
+                }
+            ),
+        };
+        format!("fn {}(c: i32, size: i32) -> i32 {{\n  {}\n}}", name, body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_all(boundary: Boundary) -> Vec<Resolved> {
+        [-1, 5, -3, 7, -10, 12, 0, 4]
+            .iter()
+            .map(|coordinate| boundary.resolve(*coordinate, 5))
+            .collect()
+    }
+
+    #[test]
+    fn wrap_forms_a_torus() {
+        use Resolved::Cell;
+        assert_eq!(
+            resolve_all(Boundary::Wrap),
+            [
+                Cell(4),
+                Cell(0),
+                Cell(2),
+                Cell(2),
+                Cell(0),
+                Cell(2),
+                Cell(0),
+                Cell(4)
+            ]
+        );
+    }
+
+    #[test]
+    fn reflect_mirrors_at_the_edge() {
+        use Resolved::Cell;
+        // Past a whole grid's width the mirror image stops at the far edge
+        assert_eq!(
+            resolve_all(Boundary::Reflect),
+            [
+                Cell(0),
+                Cell(4),
+                Cell(2),
+                Cell(2),
+                Cell(4),
+                Cell(0),
+                Cell(0),
+                Cell(4)
+            ]
+        );
+    }
+
+    #[test]
+    fn constant_boundaries_only_apply_past_the_edge() {
+        use Resolved::Cell;
+        for (boundary, past) in [
+            (Boundary::Dead, Resolved::Dead),
+            (Boundary::Alive, Resolved::Alive),
+        ] {
+            assert_eq!(
+                resolve_all(boundary),
+                [past, past, past, past, past, past, Cell(0), Cell(4)]
+            );
+        }
+    }
+
+    #[test]
+    fn skip_leaves_coordinates_alone() {
+        assert_eq!(Boundary::Skip.resolve(3, 5), Resolved::Cell(3));
+    }
+}
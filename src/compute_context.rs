@@ -0,0 +1,71 @@
+use crate::automata_error::AutomataError;
+use wgpu::{Adapter, Device, Instance, Queue};
+
+/**
+ * Everything needed to run an `Automata` without a window: the instance, adapter, device and
+ * queue. Unlike `RenderState` it never creates a surface, so it works on servers and in CI.
+ */
+pub struct ComputeContext {
+    pub instance: Instance,
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+}
+
+/**
+ * Request a device with the limits the automata needs from `adapter`.
+ */
+pub(crate) async fn request_device(adapter: &Adapter) -> Result<(Device, Queue), AutomataError> {
+    Ok(adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+            },
+            None,
+        )
+        .await?)
+}
+
+impl ComputeContext {
+    /**
+     * Create a context on the default adapter, falling back to a software adapter (such as
+     * llvmpipe or WARP) when there is no hardware one.
+     */
+    pub async fn new() -> Result<Self, AutomataError> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+
+        let request_adapter = |force_fallback_adapter| {
+            instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter,
+                compatible_surface: None,
+            })
+        };
+
+        let adapter = match request_adapter(false).await {
+            Some(adapter) => adapter,
+            None => request_adapter(true)
+                .await
+                .ok_or(AutomataError::NoAdapter)?,
+        };
+
+        let (device, queue) = request_device(&adapter).await?;
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+        })
+    }
+
+    /**
+     * `new`, blocking until the device is ready.
+     */
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_blocking() -> Result<Self, AutomataError> {
+        pollster::block_on(Self::new())
+    }
+}
@@ -0,0 +1,300 @@
+use crate::automata::{cell_count, check_state};
+use crate::automata_dsl::Rule;
+use crate::automata_error::AutomataError;
+use crate::boundary::{Boundaries, Boundary, Resolved};
+use crate::soup::random_soup;
+use glam::{IVec3, UVec3};
+
+/**
+ * Mirrors `xyz_to_id` in `compute_automata.wgsl`.
+ */
+fn xyz_to_id(dim: &UVec3, xyz: UVec3) -> usize {
+    (xyz.x + xyz.y * dim.x + xyz.z * dim.x * dim.y) as usize
+}
+
+/**
+ * The inverse of `xyz_to_id`.
+ */
+fn id_to_xyz(dim: &UVec3, id: usize) -> UVec3 {
+    let id = id as u32;
+    let automatas_in_layer = dim.x * dim.y;
+    UVec3::new(
+        (id % automatas_in_layer) % dim.x,
+        (id % automatas_in_layer) / dim.x,
+        id / automatas_in_layer,
+    )
+}
+
+/**
+ * Mirrors `neighbor_at` in `compute_automata.wgsl`: 1 if the neighbor at `point` is alive after
+ * applying the boundary of each axis, otherwise 0.
+ */
+fn neighbor_at(
+    dim: &UVec3,
+    input: &[u32],
+    rule: &Rule,
+    boundaries: &Boundaries,
+    point: IVec3,
+) -> u32 {
+    let resolved = [
+        boundaries.x.resolve(point.x, dim.x),
+        boundaries.y.resolve(point.y, dim.y),
+        boundaries.z.resolve(point.z, dim.z),
+    ];
+
+    match resolved {
+        _ if resolved.contains(&Resolved::Dead) => 0,
+        _ if resolved.contains(&Resolved::Alive) => 1,
+        [Resolved::Cell(x), Resolved::Cell(y), Resolved::Cell(z)] => {
+            (input[xyz_to_id(dim, UVec3::new(x, y, z))] == rule.alive_state) as u32
+        }
+        _ => unreachable!(),
+    }
+}
+
+/**
+ * Compute the next state of the cells `first_id..first_id + output.len()` of `input` into
+ * `output`.
+ */
+fn step_cells(
+    dim: &UVec3,
+    rule: &Rule,
+    boundaries: &Boundaries,
+    input: &[u32],
+    first_id: usize,
+    output: &mut [u32],
+) {
+    let offsets = rule.neighborhood.offsets();
+    let (min, max) = rule.neighborhood.extent();
+    let skipped = |boundary: Boundary, pos: i32, min: i32, max: i32, size: u32| {
+        boundary == Boundary::Skip && (pos + min < 0 || pos + max >= size as i32)
+    };
+
+    for (i, cell) in output.iter_mut().enumerate() {
+        let id = first_id + i;
+        let pos = id_to_xyz(dim, id).as_ivec3();
+
+        // Mirrors `neighbors` in `compute_automata.wgsl`
+        let num_neighbors = if skipped(boundaries.x, pos.x, min.x, max.x, dim.x)
+            || skipped(boundaries.y, pos.y, min.y, max.y, dim.y)
+            || skipped(boundaries.z, pos.z, min.z, max.z, dim.z)
+        {
+            0
+        } else {
+            offsets
+                .iter()
+                .map(|offset| neighbor_at(dim, input, rule, boundaries, pos + *offset))
+                .sum()
+        };
+
+        *cell = rule.eval(input[id], num_neighbors);
+    }
+}
+
+fn check_input(dim: &UVec3, input: &[u32]) {
+    assert!(
+        cell_count(dim).is_ok_and(|cells| cells as usize == input.len()),
+        "input does not match the automata dimensions"
+    );
+}
+
+/**
+ * Advance a grid by one generation on the CPU. This is a reference implementation of the compute
+ * shader and matches it exactly, including its boundary handling: cells whose neighborhood
+ * reaches past an edge with a `Boundary::Skip` boundary always see 0 neighbors.
+ */
+pub fn cpu_step(dim: &UVec3, rule: &Rule, boundaries: &Boundaries, input: &[u32]) -> Vec<u32> {
+    check_input(dim, input);
+    let mut output = vec![0; input.len()];
+    step_cells(dim, rule, boundaries, input, 0, &mut output);
+    output
+}
+
+/**
+ * An automata simulated on the CPU, split across threads. It runs the same `Rule` as `Automata`
+ * with the semantics of `cpu_step`, so the two can be used interchangeably through `Simulator`
+ * and cross-checked against each other.
+ */
+pub struct CpuAutomata {
+    pub dim: UVec3,
+    pub rule: Rule,
+    pub boundaries: Boundaries,
+    pub cells: Vec<u32>,
+    pub iteration: usize,
+    pub threads: usize,
+}
+
+impl CpuAutomata {
+    /**
+     * Create an automata of `dim` cells where each cell starts alive with probability `p`, with
+     * the same initial state as `Automata::new` given the same seed. Fails like `from_state`.
+     */
+    pub fn new(
+        dim: &UVec3,
+        p: f32,
+        seed: u64,
+        rule: Rule,
+        boundaries: Boundaries,
+    ) -> Result<Self, AutomataError> {
+        cell_count(dim)?;
+        Self::from_state(
+            dim,
+            &random_soup(dim, p, seed, rule.alive_state),
+            rule,
+            boundaries,
+        )
+    }
+
+    /**
+     * Create an automata of `dim` cells that starts in `initial_state`, using every available
+     * core. Fails, like `Automata::from_state`, if the grid is empty or has more than `u32::MAX`
+     * cells, the rule is invalid or the state does not fit the grid and rule.
+     */
+    pub fn from_state(
+        dim: &UVec3,
+        initial_state: &[u32],
+        rule: Rule,
+        boundaries: Boundaries,
+    ) -> Result<Self, AutomataError> {
+        check_state(dim, &rule, initial_state)?;
+        rule.validate()?;
+
+        Ok(Self {
+            dim: *dim,
+            rule,
+            boundaries,
+            cells: initial_state.to_vec(),
+            iteration: 0,
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        })
+    }
+
+    /**
+     * Overwrite the state of every cell. The iteration count is left alone. Fails, leaving the
+     * cells untouched, if `state` does not fit the grid and rule.
+     */
+    pub fn write_state(&mut self, state: &[u32]) -> Result<(), AutomataError> {
+        check_state(&self.dim, &self.rule, state)?;
+        self.cells = state.to_vec();
+        Ok(())
+    }
+
+    /**
+     * Advance one generation, giving each thread an equal share of the cells.
+     */
+    pub fn update(&mut self) {
+        let mut output = vec![0; self.cells.len()];
+        let chunk_size = self.cells.len().div_ceil(self.threads.max(1)).max(1);
+
+        std::thread::scope(|scope| {
+            for (chunk, output) in output.chunks_mut(chunk_size).enumerate() {
+                let (dim, rule, boundaries, input) =
+                    (&self.dim, &self.rule, &self.boundaries, &self.cells);
+                scope.spawn(move || {
+                    step_cells(dim, rule, boundaries, input, chunk * chunk_size, output)
+                });
+            }
+        });
+
+        self.cells = output;
+        self.iteration += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::Pattern;
+    use crate::rule_string::parse_rule_string;
+
+    fn grid(dim: &UVec3, pattern: &Pattern, offset: UVec3) -> Vec<u32> {
+        let mut cells = vec![0; (dim.x * dim.y * dim.z) as usize];
+        pattern.place(dim, offset, &mut cells);
+        cells
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let dim = UVec3::new(5, 5, 1);
+        let life = parse_rule_string("B3/S23").unwrap();
+        let boundaries = Boundaries::all(Boundary::Dead);
+        let horizontal = grid(&dim, &Pattern::new(3, 1, vec![1; 3]), UVec3::new(1, 2, 0));
+        let vertical = grid(&dim, &Pattern::new(1, 3, vec![1; 3]), UVec3::new(2, 1, 0));
+
+        let next = cpu_step(&dim, &life, &boundaries, &horizontal);
+        assert_eq!(next, vertical);
+        assert_eq!(cpu_step(&dim, &life, &boundaries, &next), horizontal);
+    }
+
+    #[test]
+    fn glider_wraps_around_the_torus() {
+        let dim = UVec3::new(8, 8, 1);
+        let life = parse_rule_string("B3/S23").unwrap();
+        let boundaries = Boundaries::all(Boundary::Wrap);
+        let glider = Pattern::new(3, 3, vec![0, 1, 0, 0, 0, 1, 1, 1, 1]);
+        let start = grid(&dim, &glider, UVec3::new(1, 1, 0));
+
+        // A glider moves one cell diagonally every 4 generations
+        let mut cells = start.clone();
+        for _ in 0..4 {
+            cells = cpu_step(&dim, &life, &boundaries, &cells);
+        }
+        assert_eq!(cells, grid(&dim, &glider, UVec3::new(2, 2, 0)));
+
+        for _ in 4..32 {
+            cells = cpu_step(&dim, &life, &boundaries, &cells);
+        }
+        assert_eq!(cells, start);
+    }
+
+    #[test]
+    fn threads_match_the_reference_stepper() {
+        let dim = UVec3::new(13, 11, 3);
+        let rule = parse_rule_string("B2/S/C4").unwrap();
+        let boundaries = Boundaries::all(Boundary::Wrap);
+        let mut automata = CpuAutomata::new(&dim, 0.3, 5, rule.clone(), boundaries).unwrap();
+        automata.threads = 3;
+
+        let mut cells = automata.cells.clone();
+        for _ in 0..5 {
+            automata.update();
+            cells = cpu_step(&dim, &rule, &boundaries, &cells);
+        }
+        assert_eq!(automata.cells, cells);
+        assert_eq!(automata.iteration, 5);
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized_grids() {
+        let life = parse_rule_string("B3/S23").unwrap();
+        let boundaries = Boundaries::all(Boundary::Wrap);
+        for dim in [
+            UVec3::new(4, 0, 1),
+            UVec3::new(2048, 2048, 1024),
+            UVec3::splat(u32::MAX),
+        ] {
+            assert!(matches!(
+                CpuAutomata::new(&dim, 0.3, 5, life.clone(), boundaries),
+                Err(AutomataError::InvalidDimensions(found)) if found == dim
+            ));
+            assert!(matches!(
+                CpuAutomata::from_state(&dim, &[], life.clone(), boundaries),
+                Err(AutomataError::InvalidDimensions(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn constant_boundaries_differ_at_the_edge() {
+        let dim = UVec3::new(3, 3, 1);
+        let life = parse_rule_string("B3/S23").unwrap();
+        let empty = vec![0; 9];
+
+        let dead = cpu_step(&dim, &life, &Boundaries::all(Boundary::Dead), &empty);
+        assert_eq!(dead, empty);
+
+        // Edge cells see 3 alive cells past the edge and are born, corners see 5 and are not
+        let alive = cpu_step(&dim, &life, &Boundaries::all(Boundary::Alive), &empty);
+        assert_eq!(alive, vec![0, 1, 0, 1, 0, 1, 0, 1, 0]);
+    }
+}
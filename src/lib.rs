@@ -1,10 +1,38 @@
 mod automata;
 mod automata_dsl;
+mod automata_dsl_parser;
+mod automata_dsl_types;
+mod automata_error;
+mod bit_packing;
+mod boundary;
+mod compute_context;
+mod cpu_automata;
+mod neighborhood;
+mod pattern;
+mod pattern_file;
 mod render_state;
+mod rule_string;
 mod simple_camera;
+mod simulator;
+mod snapshot;
+mod soup;
 pub mod util;
 
 pub use automata::*;
 pub use automata_dsl::*;
+pub use automata_dsl_parser::*;
+pub use automata_dsl_types::*;
+pub use automata_error::*;
+pub use bit_packing::*;
+pub use boundary::*;
+pub use compute_context::*;
+pub use cpu_automata::*;
+pub use neighborhood::*;
+pub use pattern::*;
+pub use pattern_file::*;
 pub use render_state::*;
+pub use rule_string::*;
 pub use simple_camera::*;
+pub use simulator::*;
+pub use snapshot::*;
+pub use soup::*;
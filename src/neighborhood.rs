@@ -0,0 +1,208 @@
+use crate::boundary::{Boundaries, Boundary};
+use glam::IVec3;
+use std::collections::HashSet;
+
+/**
+ * The set of cells, relative to a cell, that are counted as its neighbors. The cell itself is
+ * never part of its own neighborhood. 2D neighborhoods only look at cells in the same z layer.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Neighborhood {
+    /* The 8 cells of the 3x3 square around the cell */
+    Moore2D,
+    /* The 4 cells that share an edge with the cell */
+    VonNeumann2D,
+    /* The 26 cells of the 3x3x3 cube around the cell */
+    Moore3D,
+    /* The 6 cells that share a face with the cell */
+    VonNeumann3D,
+    /* Every cell within the given Chebyshev distance in the same layer */
+    Box2D(u32),
+    /* Every cell within the given Chebyshev distance */
+    Box3D(u32),
+    /* Every cell within the given Manhattan distance in the same layer */
+    Diamond2D(u32),
+    /* Every cell within the given Manhattan distance */
+    Diamond3D(u32),
+    /* An arbitrary list of offsets */
+    Offsets(Vec<IVec3>),
+}
+
+impl Neighborhood {
+    /**
+     * The position of every neighbor relative to the cell, without duplicates and without the
+     * cell itself.
+     */
+    pub fn offsets(&self) -> Vec<IVec3> {
+        let square = |radius: u32| {
+            let radius = radius as i32;
+            (-radius..=radius)
+                .flat_map(move |y| (-radius..=radius).map(move |x| IVec3::new(x, y, 0)))
+                .collect::<Vec<IVec3>>()
+        };
+
+        let cube = |radius: u32| {
+            let radius = radius as i32;
+            (-radius..=radius)
+                .flat_map(move |z| {
+                    (-radius..=radius)
+                        .flat_map(move |y| (-radius..=radius).map(move |x| IVec3::new(x, y, z)))
+                })
+                .collect::<Vec<IVec3>>()
+        };
+
+        let within_manhattan = |offsets: Vec<IVec3>, radius: u32| {
+            offsets
+                .into_iter()
+                .filter(|offset| offset.abs().dot(IVec3::ONE) <= radius as i32)
+                .collect::<Vec<IVec3>>()
+        };
+
+        let mut offsets = match self {
+            Neighborhood::Moore2D => square(1),
+            Neighborhood::VonNeumann2D => within_manhattan(square(1), 1),
+            Neighborhood::Moore3D => cube(1),
+            Neighborhood::VonNeumann3D => within_manhattan(cube(1), 1),
+            Neighborhood::Box2D(radius) => square(*radius),
+            Neighborhood::Box3D(radius) => cube(*radius),
+            Neighborhood::Diamond2D(radius) => within_manhattan(square(*radius), *radius),
+            Neighborhood::Diamond3D(radius) => within_manhattan(cube(*radius), *radius),
+            Neighborhood::Offsets(offsets) => offsets.clone(),
+        };
+
+        let mut seen = HashSet::new();
+        offsets.retain(|offset| *offset != IVec3::ZERO && seen.insert(*offset));
+        offsets
+    }
+
+    /**
+     * The largest number of neighbors a cell can have.
+     */
+    pub fn max_neighbors(&self) -> u32 {
+        self.offsets().len() as u32
+    }
+
+    /**
+     * How far the neighborhood reaches in the negative and positive direction of each axis, as
+     * the component-wise minimum and maximum of the offsets.
+     */
+    pub fn extent(&self) -> (IVec3, IVec3) {
+        self.offsets()
+            .iter()
+            .fold((IVec3::ZERO, IVec3::ZERO), |(min, max), offset| {
+                (min.min(*offset), max.max(*offset))
+            })
+    }
+
+    /**
+     * True if every neighbor is in the same z layer as the cell.
+     */
+    pub fn is_planar(&self) -> bool {
+        self.offsets().iter().all(|offset| offset.z == 0)
+    }
+
+    /**
+     * Generate the body of `neighbors` in the compute shader, which counts the alive neighbors of
+     * the cell at `pos`. Cells whose neighborhood reaches past an edge with a `Boundary::Skip`
+     * boundary see no neighbors. Axes the neighborhood does not reach along are never checked, so
+     * a 2D neighborhood updates every layer of a 3D grid.
+     */
+    pub fn to_shader(&self, boundaries: &Boundaries) -> String {
+        let (min, max) = self.extent();
+
+        let mut bounds = Vec::new();
+        for (axis, boundary, min, max) in [
+            ("x", boundaries.x, min.x, max.x),
+            ("y", boundaries.y, min.y, max.y),
+            ("z", boundaries.z, min.z, max.z),
+        ] {
+            if boundary != Boundary::Skip {
+                continue;
+            }
+            if min < 0 {
+                bounds.push(format!("pos.{} < {}u", axis, -min));
+            }
+            if max > 0 {
+                bounds.push(format!("pos.{} + {}u >= automata_dim.{}", axis, max, axis));
+            }
+        }
+
+        let counts = self
+            .offsets()
+            .iter()
+            .map(|offset| {
+                format!(
+                    "result += neighbor_at(pos, vec3<i32>({}, {}, {}));",
+                    offset.x, offset.y, offset.z
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n  ");
+
+        let mut body = String::new();
+        if !bounds.is_empty() {
+            body += &format!("if {} {{\n    return 0u;\n  }}\n\n  ", bounds.join(" || "));
+        }
+        body += &format!("var result: u32 = 0u;\n  {}\n  return result;", counts);
+        body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_neighborhood_sizes() {
+        assert_eq!(Neighborhood::Moore2D.offsets().len(), 8);
+        assert_eq!(Neighborhood::VonNeumann2D.offsets().len(), 4);
+        assert_eq!(Neighborhood::Moore3D.offsets().len(), 26);
+        assert_eq!(Neighborhood::VonNeumann3D.offsets().len(), 6);
+        assert_eq!(Neighborhood::Box2D(2).max_neighbors(), 24);
+        assert_eq!(Neighborhood::Box3D(2).max_neighbors(), 124);
+        assert_eq!(Neighborhood::Diamond2D(2).max_neighbors(), 12);
+        assert_eq!(Neighborhood::Diamond3D(2).max_neighbors(), 24);
+        assert_eq!(
+            Neighborhood::Box2D(1).offsets(),
+            Neighborhood::Moore2D.offsets()
+        );
+        assert_eq!(
+            Neighborhood::Diamond3D(1).offsets(),
+            Neighborhood::VonNeumann3D.offsets()
+        );
+    }
+
+    #[test]
+    fn offsets_drop_duplicates_and_the_cell_itself() {
+        let (left, up) = (IVec3::new(-1, 0, 0), IVec3::new(0, 2, 0));
+        let neighborhood = Neighborhood::Offsets(vec![left, IVec3::ZERO, up, left]);
+        assert_eq!(neighborhood.offsets(), vec![left, up]);
+        assert_eq!(neighborhood.max_neighbors(), 2);
+    }
+
+    #[test]
+    fn planar_neighborhoods_stay_in_their_layer() {
+        assert!(Neighborhood::Moore2D.is_planar());
+        assert!(Neighborhood::Diamond2D(3).is_planar());
+        assert!(!Neighborhood::VonNeumann3D.is_planar());
+        assert!(Neighborhood::Offsets(vec![IVec3::new(1, 1, 0)]).is_planar());
+        assert!(!Neighborhood::Offsets(vec![IVec3::new(0, 0, -1)]).is_planar());
+    }
+
+    #[test]
+    fn extent_bounds_the_offsets() {
+        assert_eq!(
+            Neighborhood::Moore2D.extent(),
+            (IVec3::new(-1, -1, 0), IVec3::new(1, 1, 0))
+        );
+        assert_eq!(
+            Neighborhood::Box3D(2).extent(),
+            (IVec3::splat(-2), IVec3::splat(2))
+        );
+        // The cell itself is always inside the extent
+        assert_eq!(
+            Neighborhood::Offsets(vec![IVec3::new(2, 3, 0), IVec3::new(1, -1, 0)]).extent(),
+            (IVec3::new(0, -1, 0), IVec3::new(2, 3, 0))
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::automata_dsl::Rule;
+use crate::rule_string::parse_rule_string;
+use glam::UVec3;
+
+/**
+ * A rectangular 2D block of cell states that can be placed into a grid. Cells are stored row by
+ * row, so the state of the cell at `(x, y)` is `cells[x + y * width]`. Patterns loaded from a file
+ * keep the rule string the file was written for, if it names one.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<u32>,
+    pub rule_string: Option<String>,
+}
+
+impl Pattern {
+    pub fn new(width: u32, height: u32, cells: Vec<u32>) -> Self {
+        assert_eq!(
+            cells.len(),
+            (width * height) as usize,
+            "a {}x{} pattern needs {} cells",
+            width,
+            height,
+            width * height
+        );
+        Self {
+            width,
+            height,
+            cells,
+            rule_string: None,
+        }
+    }
+
+    /**
+     * The rule the pattern was written for, if it has a rule string that `parse_rule_string`
+     * understands. Golly's bounded grid suffix (e.g. `:T100,100`) is ignored.
+     */
+    pub fn rule(&self) -> Option<Rule> {
+        let rule_string = self.rule_string.as_ref()?;
+        let rule_string = rule_string.split(':').next().unwrap_or(rule_string);
+        parse_rule_string(rule_string).ok()
+    }
+
+    /**
+     * The z layer `z` of `state`, a grid of `dim` cells, as a pattern.
+     */
+    pub fn from_layer(dim: &UVec3, state: &[u32], z: u32) -> Self {
+        let layer = (dim.x * dim.y) as usize;
+        let start = z as usize * layer;
+        Self::new(dim.x, dim.y, state[start..start + layer].to_vec())
+    }
+
+    /**
+     * The smallest part of the pattern that holds every cell that is not dead. A pattern with no
+     * such cells crops to 0x0.
+     */
+    pub fn cropped(&self) -> Self {
+        let live = || {
+            (0..self.height)
+                .flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+                .filter(|(x, y)| self.get(*x, *y) != 0)
+        };
+
+        let (min_x, min_y, max_x, max_y) = match live().next() {
+            Some((x, y)) => live().fold((x, y, x, y), |(min_x, min_y, max_x, max_y), (x, y)| {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            }),
+            None => {
+                return Self {
+                    rule_string: self.rule_string.clone(),
+                    ..Self::new(0, 0, Vec::new())
+                }
+            }
+        };
+
+        let cells = (min_y..=max_y)
+            .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(x, y))
+            .collect();
+
+        Self {
+            rule_string: self.rule_string.clone(),
+            ..Self::new(max_x - min_x + 1, max_y - min_y + 1, cells)
+        }
+    }
+
+    /**
+     * The state of the cell at `(x, y)`.
+     */
+    pub fn get(&self, x: u32, y: u32) -> u32 {
+        self.cells[(x + y * self.width) as usize]
+    }
+
+    /**
+     * True if the pattern fits in a grid of `dim` cells with its top left corner at `offset`.
+     */
+    pub fn fits(&self, dim: &UVec3, offset: UVec3) -> bool {
+        let fits_axis = |offset: u32, size: u32, dim: u32| {
+            offset.checked_add(size).is_some_and(|end| end <= dim)
+        };
+        fits_axis(offset.x, self.width, dim.x)
+            && fits_axis(offset.y, self.height, dim.y)
+            && offset.z < dim.z
+    }
+
+    /**
+     * Write the pattern into `state`, a grid of `dim` cells, with its top left corner at `offset`.
+     * Only the z layer `offset.z` is touched. Panics if the pattern does not fit in the grid.
+     */
+    pub fn place(&self, dim: &UVec3, offset: UVec3, state: &mut [u32]) {
+        assert!(
+            self.fits(dim, offset),
+            "a {}x{} pattern at {} does not fit in a grid of {}",
+            self.width,
+            self.height,
+            offset,
+            dim
+        );
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let id = (offset.x + x) + (offset.y + y) * dim.x + offset.z * dim.x * dim.y;
+                state[id as usize] = self.get(x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,588 @@
+use crate::pattern::Pattern;
+use std::fmt;
+use std::str::FromStr;
+
+/**
+ * A pattern file that could not be read. The line is 1-based.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl PatternError {
+    fn new(line: usize, message: String) -> Self {
+        Self { line, message }
+    }
+}
+
+/* The largest pattern a file may describe, to avoid allocating a huge grid for a typo */
+const MAX_PATTERN_CELLS: u64 = 1 << 28;
+
+fn check_size(line: usize, width: u64, height: u64) -> Result<(), PatternError> {
+    // The dimensions come from untrusted files, so their product may not even fit in a u64
+    if width
+        .checked_mul(height)
+        .is_none_or(|cells| cells > MAX_PATTERN_CELLS)
+    {
+        return Err(PatternError::new(
+            line,
+            format!("a {}x{} pattern is too large", width, height),
+        ));
+    }
+    Ok(())
+}
+
+/**
+ * The state of an RLE cell tag. `b` and `.` are dead, `o` is state 1, `A` to `X` are states 1 to
+ * 24, and a prefix `p` to `y` adds 24 for each letter past `o`, so `pA` is state 25.
+ */
+fn rle_state(prefix: Option<char>, tag: char) -> Option<u32> {
+    let letter = |c: char| match c {
+        'A'..='X' => Some(c as u32 - 'A' as u32 + 1),
+        _ => None,
+    };
+    match prefix {
+        None => match tag {
+            'b' | '.' => Some(0),
+            'o' => Some(1),
+            _ => letter(tag),
+        },
+        Some(prefix @ 'p'..='y') => Some((prefix as u32 - 'p' as u32 + 1) * 24 + letter(tag)?),
+        Some(_) => None,
+    }
+}
+
+/**
+ * Parse the `x = m, y = n, rule = abc` header line of an RLE file into the width, height and rule
+ * string. The rule is the rest of the line, as bounded grid suffixes such as `:T10,10` contain
+ * commas.
+ */
+fn parse_rle_header(line: usize, header: &str) -> Result<(u32, u32, Option<String>), PatternError> {
+    let (mut width, mut height, mut rule) = (None, None, None);
+    let mut rest = header;
+    while !rest.trim().is_empty() {
+        let (field, next) = rest.split_once(',').unwrap_or((rest, ""));
+        rest = next;
+        let (key, value) = field.split_once('=').ok_or_else(|| {
+            PatternError::new(
+                line,
+                format!("expected `key = value` but found `{}`", field.trim()),
+            )
+        })?;
+        let (key, value) = (key.trim(), value.trim());
+        let number = || {
+            value
+                .parse::<u32>()
+                .map_err(|_| PatternError::new(line, format!("`{}` is not a valid {}", value, key)))
+        };
+        match key {
+            "x" => width = Some(number()?),
+            "y" => height = Some(number()?),
+            "rule" => {
+                rule = Some(
+                    format!("{},{}", value, rest)
+                        .trim_end_matches(',')
+                        .trim()
+                        .to_string(),
+                );
+                break;
+            }
+            _ => {}
+        }
+    }
+    match (width, height) {
+        (Some(width), Some(height)) => {
+            check_size(line, width as u64, height as u64)?;
+            Ok((width, height, rule))
+        }
+        _ => Err(PatternError::new(
+            line,
+            "the header needs both `x` and `y`".to_string(),
+        )),
+    }
+}
+
+/**
+ * Parse a pattern in Golly's run length encoded format, e.g.
+ *
+ * ```text
+ * #N Glider
+ * x = 3, y = 3, rule = B3/S23
+ * bo$2bo$3o!
+ * ```
+ *
+ * `#` lines before the header are comments. Runs of `b` or `.` are dead cells, `o` or `A` to `X`
+ * (with an optional `p` to `y` prefix for states past 24) are live cells in that state, `$` ends a
+ * row and `!` ends the pattern. Rows shorter than the header's width are padded with dead cells.
+ * The header's `rule` is kept as the pattern's rule string.
+ */
+pub fn parse_rle(text: &str) -> Result<Pattern, PatternError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let (header_line, header) = lines
+        .next()
+        .ok_or_else(|| PatternError::new(1, "missing the `x = .., y = ..` header".to_string()))?;
+    let (width, height, rule_string) = parse_rle_header(header_line, header)?;
+
+    let mut cells = vec![0; (width * height) as usize];
+    let (mut x, mut y) = (0u32, 0u32);
+    let mut count: Option<u32> = None;
+    let mut prefix: Option<char> = None;
+
+    'lines: for (line, text) in lines {
+        for c in text.chars() {
+            let error = |message: String| Err(PatternError::new(line, message));
+            match c {
+                _ if c.is_whitespace() => continue,
+                '!' => break 'lines,
+                '0'..='9' if prefix.is_none() => {
+                    let digit = c.to_digit(10).unwrap();
+                    count = match count.unwrap_or(0).checked_mul(10) {
+                        Some(count) => count.checked_add(digit),
+                        None => None,
+                    };
+                    if count.is_none() {
+                        return error("run length is too large".to_string());
+                    }
+                }
+                '$' if prefix.is_none() => {
+                    y = y.saturating_add(count.take().unwrap_or(1));
+                    x = 0;
+                }
+                'p'..='y' if prefix.is_none() && c != 'o' => prefix = Some(c),
+                _ => {
+                    let state = match rle_state(prefix.take(), c) {
+                        Some(state) => state,
+                        None => return error(format!("`{}` is not a cell state", c)),
+                    };
+                    let run = count.take().unwrap_or(1);
+                    if x as u64 + run as u64 > width as u64 || (state != 0 && y >= height) {
+                        return error(format!(
+                            "the pattern does not fit in the {}x{} size of its header",
+                            width, height
+                        ));
+                    }
+                    if state != 0 {
+                        for dx in 0..run {
+                            cells[(x + dx + y * width) as usize] = state;
+                        }
+                    }
+                    x += run;
+                }
+            }
+        }
+    }
+
+    Ok(Pattern {
+        rule_string,
+        ..Pattern::new(width, height, cells)
+    })
+}
+
+/**
+ * Parse a pattern in the Life 1.06 format: a `#Life 1.06` line followed by one `x y` coordinate
+ * per live cell. The coordinates may be negative, the pattern is the bounding box of the live
+ * cells.
+ */
+pub fn parse_life_106(text: &str) -> Result<Pattern, PatternError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()));
+
+    match lines.next() {
+        Some((_, header)) if header.starts_with("#Life 1.06") => {}
+        _ => {
+            return Err(PatternError::new(
+                1,
+                "expected the `#Life 1.06` header".to_string(),
+            ))
+        }
+    }
+
+    let mut live_cells = Vec::new();
+    for (line, text) in lines.filter(|(_, line)| !line.is_empty() && !line.starts_with('#')) {
+        let coordinates = text
+            .split_whitespace()
+            .map(|c| c.parse::<i64>())
+            .collect::<Result<Vec<i64>, _>>();
+        match coordinates.as_deref() {
+            Ok([x, y]) => live_cells.push((*x, *y)),
+            _ => {
+                return Err(PatternError::new(
+                    line,
+                    format!("expected an `x y` coordinate but found `{}`", text),
+                ))
+            }
+        }
+    }
+
+    if live_cells.is_empty() {
+        return Ok(Pattern::new(0, 0, Vec::new()));
+    }
+
+    let min_x = live_cells.iter().map(|(x, _)| *x).min().unwrap();
+    let max_x = live_cells.iter().map(|(x, _)| *x).max().unwrap();
+    let min_y = live_cells.iter().map(|(_, y)| *y).min().unwrap();
+    let max_y = live_cells.iter().map(|(_, y)| *y).max().unwrap();
+    let extent = |min: i64, max: i64| {
+        max.checked_sub(min)
+            .and_then(|span| span.checked_add(1))
+            .map(|extent| extent as u64)
+    };
+    let (width, height) = match (extent(min_x, max_x), extent(min_y, max_y)) {
+        (Some(width), Some(height)) => (width, height),
+        _ => {
+            return Err(PatternError::new(
+                1,
+                "the coordinates span too large a pattern".to_string(),
+            ))
+        }
+    };
+    check_size(1, width, height)?;
+
+    let mut cells = vec![0; (width * height) as usize];
+    for (x, y) in live_cells {
+        cells[((x - min_x) as u64 + (y - min_y) as u64 * width) as usize] = 1;
+    }
+
+    Ok(Pattern::new(width as u32, height as u32, cells))
+}
+
+/**
+ * Parse a pattern in the plaintext `.cells` format: `!` lines are comments and every other line
+ * is a row where `.` is a dead cell and `O` (or `*`) is a live cell. Rows shorter than the
+ * longest row are padded with dead cells.
+ */
+pub fn parse_plaintext(text: &str) -> Result<Pattern, PatternError> {
+    let rows: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end()))
+        .filter(|(_, line)| !line.starts_with('!'))
+        .collect();
+
+    // Trailing blank rows are not part of the pattern
+    let rows = match rows.iter().rposition(|(_, row)| !row.is_empty()) {
+        Some(last) => &rows[..=last],
+        None => &[],
+    };
+
+    let width = rows
+        .iter()
+        .map(|(_, row)| row.chars().count())
+        .max()
+        .unwrap_or(0) as u64;
+    let height = rows.len() as u64;
+    check_size(1, width, height)?;
+
+    let mut cells = vec![0; (width * height) as usize];
+    for (y, (line, row)) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            match c {
+                '.' => {}
+                'O' | '*' => cells[x + y * width as usize] = 1,
+                _ => {
+                    return Err(PatternError::new(
+                        *line,
+                        format!("`{}` is not a cell, expected `.` or `O`", c),
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(Pattern::new(width as u32, height as u32, cells))
+}
+
+/**
+ * Parse a pattern in any of the supported formats. Life 1.06 files are recognised by their
+ * header, files whose first line that is not a `#` comment starts with `x` are RLE, and anything
+ * else is plaintext.
+ */
+pub fn parse_pattern(text: &str) -> Result<Pattern, PatternError> {
+    if text.trim_start().starts_with("#Life 1.06") {
+        return parse_life_106(text);
+    }
+
+    let first_line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'));
+
+    match first_line {
+        Some(line) if line.starts_with('x') => parse_rle(text),
+        _ => parse_plaintext(text),
+    }
+}
+
+/* Golly keeps RLE lines at most this long */
+const MAX_RLE_LINE: usize = 70;
+
+/* The largest state an RLE tag can express, `yX` */
+pub const MAX_RLE_STATE: u32 = 264;
+
+/**
+ * A pattern with a cell in a state that the format it is being written in cannot express.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedStateError {
+    pub state: u32,
+    pub max_state: u32,
+}
+
+impl fmt::Display for UnsupportedStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a cell is in state {} but the format can express at most state {}",
+            self.state, self.max_state
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedStateError {}
+
+/**
+ * The RLE tag for a cell state, the inverse of `rle_state`. Two state patterns use `b` and `o`.
+ */
+fn rle_tag(state: u32, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (1..=24, true) => char::from(b'A' + (state - 1) as u8).to_string(),
+        _ => {
+            let prefix = char::from(b'p' + ((state - 1) / 24 - 1) as u8);
+            let letter = char::from(b'A' + ((state - 1) % 24) as u8);
+            format!("{}{}", prefix, letter)
+        }
+    }
+}
+
+/**
+ * Write a pattern in Golly's run length encoded format, with a `rule` in the header if the
+ * pattern has a rule string. Patterns with cells past state 1 are written with the multi-state
+ * tags. Fails if a cell is past `MAX_RLE_STATE`, the largest state RLE can express.
+ */
+pub fn write_rle(pattern: &Pattern) -> Result<String, UnsupportedStateError> {
+    let max_state = pattern.cells.iter().copied().max().unwrap_or(0);
+    if max_state > MAX_RLE_STATE {
+        return Err(UnsupportedStateError {
+            state: max_state,
+            max_state: MAX_RLE_STATE,
+        });
+    }
+    let multi_state = max_state > 1;
+
+    let mut runs: Vec<(u32, String)> = Vec::new();
+    let mut push = |count: u32, tag: String| match runs.last_mut() {
+        Some((run, last)) if *last == tag => *run += count,
+        _ => runs.push((count, tag)),
+    };
+
+    for y in 0..pattern.height {
+        if y > 0 {
+            push(1, "$".to_string());
+        }
+        let row = &pattern.cells[(y * pattern.width) as usize..((y + 1) * pattern.width) as usize];
+        // Dead cells at the end of a row are implied
+        let end = row.iter().rposition(|cell| *cell != 0).map_or(0, |x| x + 1);
+        for cell in &row[..end] {
+            push(1, rle_tag(*cell, multi_state));
+        }
+    }
+
+    // Dead rows at the end are implied by the height
+    if matches!(runs.last(), Some((_, tag)) if tag == "$") {
+        runs.pop();
+    }
+
+    let mut text = format!("x = {}, y = {}", pattern.width, pattern.height);
+    if let Some(rule) = &pattern.rule_string {
+        text += &format!(", rule = {}", rule);
+    }
+    text += "\n";
+
+    let mut line = String::new();
+    for item in runs
+        .iter()
+        .map(|(count, tag)| match count {
+            1 => tag.clone(),
+            _ => format!("{}{}", count, tag),
+        })
+        .chain(std::iter::once("!".to_string()))
+    {
+        if line.len() + item.len() > MAX_RLE_LINE {
+            text += &line;
+            text += "\n";
+            line.clear();
+        }
+        line += &item;
+    }
+    text += &line;
+    text += "\n";
+    Ok(text)
+}
+
+/**
+ * Write a pattern in the Life 1.06 format, one `x y` line per cell in state 1. Life 1.06 has no
+ * other states, so cells in any other state are left out.
+ */
+pub fn write_life_106(pattern: &Pattern) -> String {
+    let mut text = "#Life 1.06\n".to_string();
+    for y in 0..pattern.height {
+        for x in 0..pattern.width {
+            if pattern.get(x, y) == 1 {
+                text += &format!("{} {}\n", x, y);
+            }
+        }
+    }
+    text
+}
+
+impl FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_pattern(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glider() -> Pattern {
+        Pattern::new(3, 3, vec![0, 1, 0, 0, 0, 1, 1, 1, 1])
+    }
+
+    #[test]
+    fn parses_rle() {
+        let pattern = parse_rle("#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n").unwrap();
+        assert_eq!(pattern.cells, glider().cells);
+        assert_eq!(pattern.rule_string.as_deref(), Some("B3/S23"));
+
+        // Runs may span lines, and rows shorter than the header are padded
+        let pattern = parse_rle("x = 4, y = 3\n2o$\n$b\n3o!").unwrap();
+        assert_eq!(pattern.cells, vec![1, 1, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1]);
+        assert_eq!(pattern.rule_string, None);
+    }
+
+    #[test]
+    fn parses_rle_header_with_bounded_grid() {
+        let pattern = parse_rle("x = 2, y = 1, rule = B3/S23:T10,10\n2o!").unwrap();
+        assert_eq!(pattern.rule_string.as_deref(), Some("B3/S23:T10,10"));
+        assert!(pattern.rule().is_some());
+    }
+
+    #[test]
+    fn parses_multi_state_rle() {
+        let pattern = parse_rle("x = 4, y = 1, rule = B2/S/C3\n.ABpA!").unwrap();
+        assert_eq!(pattern.cells, vec![0, 1, 2, 25]);
+        assert_eq!(pattern.rule().unwrap().states, 3);
+    }
+
+    #[test]
+    fn rejects_invalid_rle() {
+        let line = |text: &str| parse_rle(text).unwrap_err().line;
+        assert_eq!(line(""), 1);
+        assert_eq!(line("x = 3\nbo!"), 1);
+        assert_eq!(line("x = 3, y = 1\n#C comment\n4o!"), 3);
+        assert_eq!(line("x = 3, y = 1\no$o!"), 2);
+        assert_eq!(line("x = 3, y = 1\nbz!"), 2);
+        assert_eq!(line("x = 3, y = 1\n99999999999o!"), 2);
+        assert_eq!(line("x = 4294967295, y = 4294967295\n!"), 1);
+    }
+
+    #[test]
+    fn parses_life_106() {
+        let pattern = parse_life_106("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+        assert_eq!(pattern.cells, glider().cells);
+        assert_eq!(parse_life_106("#Life 1.06\n").unwrap().width, 0);
+        assert_eq!(parse_life_106("0 0").unwrap_err().line, 1);
+        assert_eq!(parse_life_106("#Life 1.06\n0 0\n1\n").unwrap_err().line, 3);
+    }
+
+    #[test]
+    fn rejects_life_106_spanning_too_many_cells() {
+        let text = format!("#Life 1.06\n{} 0\n{} 0\n", i64::MIN, i64::MAX);
+        assert!(parse_life_106(&text).is_err());
+        assert!(parse_life_106("#Life 1.06\n0 0\n100000 100000\n").is_err());
+    }
+
+    #[test]
+    fn parses_plaintext() {
+        let pattern = parse_plaintext("!Name: Glider\n.O\n..O\nOOO\n\n").unwrap();
+        assert_eq!(pattern.cells, glider().cells);
+        assert_eq!(parse_plaintext("!\n.O\nO?\n").unwrap_err().line, 3);
+    }
+
+    #[test]
+    fn detects_the_format() {
+        for text in [
+            "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n",
+            "#N Glider\nx = 3, y = 3\nbo$2bo$3o!",
+            ".O\n..O\nOOO\n",
+        ] {
+            assert_eq!(text.parse::<Pattern>().unwrap().cells, glider().cells);
+        }
+    }
+
+    #[test]
+    fn written_patterns_round_trip() {
+        let pattern = Pattern {
+            rule_string: Some("B3/S23".to_string()),
+            ..glider()
+        };
+        let rle = write_rle(&pattern).unwrap();
+        assert_eq!(rle, "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+        assert_eq!(parse_rle(&rle).unwrap(), pattern);
+
+        assert_eq!(
+            parse_life_106(&write_life_106(&glider())).unwrap(),
+            glider()
+        );
+
+        // Trailing dead rows and columns are implied by the header
+        let sparse = Pattern::new(40, 4, (0..160).map(|i| (i % 7 == 0) as u32).collect());
+        let sparse = Pattern::new(40, 5, [sparse.cells, vec![0; 40]].concat());
+        let rle = write_rle(&sparse).unwrap();
+        assert_eq!(parse_rle(&rle).unwrap(), sparse);
+        assert!(rle.lines().all(|line| line.len() <= MAX_RLE_LINE));
+    }
+
+    #[test]
+    fn multi_state_patterns_round_trip() {
+        let pattern = Pattern::new(5, 2, vec![0, 1, 2, 24, 25, 48, 49, 264, 0, 0]);
+        let rle = write_rle(&pattern).unwrap();
+        assert_eq!(rle, "x = 5, y = 2\n.ABXpA$pXqAyX!\n");
+        assert_eq!(parse_rle(&rle).unwrap(), pattern);
+    }
+
+    #[test]
+    fn rejects_states_rle_cannot_express() {
+        let pattern = Pattern::new(2, 1, vec![1, MAX_RLE_STATE + 1]);
+        assert_eq!(
+            write_rle(&pattern),
+            Err(UnsupportedStateError {
+                state: MAX_RLE_STATE + 1,
+                max_state: MAX_RLE_STATE,
+            })
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::automata_error::AutomataError;
 use glam::Mat4;
 use wgpu::{
     BindGroup, BindGroupLayout, Buffer, Device, Instance, Queue, Surface, SurfaceConfiguration,
@@ -24,7 +25,7 @@ pub struct RenderState {
 }
 
 impl RenderState {
-    pub async fn new(window: &Window) -> Self {
+    pub async fn new(window: &Window) -> Result<Self, AutomataError> {
         let size = window.inner_size();
         let instance = wgpu::Instance::new(wgpu::Backends::all());
         let surface = unsafe { instance.create_surface(&window) };
@@ -36,19 +37,9 @@ impl RenderState {
                 compatible_surface: Some(&surface),
             })
             .await
-            .expect("Failed to find an appropriate adapter");
+            .ok_or(AutomataError::NoAdapter)?;
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
-                },
-                None,
-            )
-            .await
-            .expect("Failed to create device");
+        let (device, queue) = crate::compute_context::request_device(&adapter).await?;
 
         let swapchain_format = surface.get_supported_formats(&adapter)[0];
 
@@ -91,7 +82,7 @@ impl RenderState {
         let (depth_buffer, depth_buffer_view) =
             crate::util::generate_depth_buffer(&device, &config);
 
-        RenderState {
+        Ok(RenderState {
             instance,
             surface,
             swapchain_format,
@@ -103,7 +94,7 @@ impl RenderState {
             general_bind_group_layout,
             general_bind_group,
             projection_buffer,
-        }
+        })
     }
 
     pub fn regenerate_depth_buffer(&mut self) {
@@ -0,0 +1,406 @@
+use crate::automata_dsl::{rulesets, Rule};
+use crate::neighborhood::Neighborhood;
+use std::fmt;
+
+/**
+ * A rule string that could not be understood.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleStringError {
+    pub rule: String,
+    pub message: String,
+}
+
+impl fmt::Display for RuleStringError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid rule string `{}`: {}", self.rule, self.message)
+    }
+}
+
+impl std::error::Error for RuleStringError {}
+
+impl RuleStringError {
+    fn new(rule: &str, message: String) -> Self {
+        Self {
+            rule: rule.to_string(),
+            message,
+        }
+    }
+}
+
+/* The largest neighbor count in a 2D Moore neighborhood */
+const MAX_NEIGHBORS_2D: u32 = 8;
+
+/**
+ * Parse the digits of a birth or survival list, e.g. `23` into [2, 3].
+ */
+fn parse_counts(rule: &str, digits: &str) -> Result<Vec<u32>, RuleStringError> {
+    let mut counts = Vec::new();
+    for c in digits.chars() {
+        let count = c.to_digit(10).ok_or_else(|| {
+            RuleStringError::new(rule, format!("`{}` is not a neighbor count", c))
+        })?;
+        if count > MAX_NEIGHBORS_2D {
+            return Err(RuleStringError::new(
+                rule,
+                format!(
+                    "{} is more neighbors than a cell has (at most {})",
+                    count, MAX_NEIGHBORS_2D
+                ),
+            ));
+        }
+        if !counts.contains(&count) {
+            counts.push(count);
+        }
+    }
+    counts.sort_unstable();
+    Ok(counts)
+}
+
+fn parse_states(rule: &str, digits: &str) -> Result<u32, RuleStringError> {
+    if digits.is_empty() {
+        return Err(RuleStringError::new(
+            rule,
+            "missing the number of states".to_string(),
+        ));
+    }
+    let states = digits.parse::<u32>().map_err(|_| {
+        RuleStringError::new(rule, format!("`{}` is not a number of states", digits))
+    })?;
+    if states < 2 {
+        return Err(RuleStringError::new(
+            rule,
+            format!("a rule needs at least 2 states but {} was given", states),
+        ));
+    }
+    Ok(states)
+}
+
+/**
+ * Parse a 2D life-like or Generations rule string into a `Rule`. The accepted notations are:
+ *
+ *  - B/S notation, e.g. `B3/S23`, in either order and any case (`S23/B3`, `b36/s23`).
+ *  - Golly / MCell S/B notation without letters, e.g. `23/3`.
+ *  - Generations rules with a trailing state count, either lettered (`B2/S/C3`) or not
+ *    (`345/2/4`, which is survival / birth / states).
+ *
+ * The rule counts neighbors in the 2D Moore neighborhood. Two state rules produce the same
+ * statement as writing the rule by hand, e.g. `B3/S23` gives `rulesets::conways_game_of_life()`.
+ * Rules with more states decay as described in `rulesets::generations`. The rule's notation is
+ * the canonical B/S form of the rule string, e.g. `B3/S23` or `B2/S/C3`.
+ */
+pub fn parse_rule_string(rule: &str) -> Result<Rule, RuleStringError> {
+    let error = |message: &str| RuleStringError::new(rule, message.to_string());
+
+    let parts: Vec<&str> = rule.trim().split('/').map(str::trim).collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return Err(error("expected two or three parts separated by `/`"));
+    }
+
+    let lettered = parts
+        .iter()
+        .any(|part| part.starts_with(|c: char| c.is_ascii_alphabetic()));
+
+    let (survive, birth, states) = if lettered {
+        let (mut survive, mut birth, mut states) = (None, None, None);
+        for (i, part) in parts.iter().enumerate() {
+            let mut chars = part.chars();
+            let letter = chars.next().map(|c| c.to_ascii_uppercase());
+            let rest = chars.as_str();
+            let (slot, name) = match letter {
+                Some('S') => (&mut survive, "S"),
+                Some('B') => (&mut birth, "B"),
+                Some('C') | Some('G') => {
+                    if states.is_some() {
+                        return Err(error("the state count is given twice"));
+                    }
+                    states = Some(parse_states(rule, rest)?);
+                    continue;
+                }
+                // A bare number after the B and S parts is a state count, as in `B2/S/3`
+                Some(c) if c.is_ascii_digit() && states.is_none() && i == parts.len() - 1 => {
+                    states = Some(parse_states(rule, part)?);
+                    continue;
+                }
+                _ => {
+                    return Err(RuleStringError::new(
+                        rule,
+                        format!("`{}` should start with `B`, `S` or `C`", part),
+                    ))
+                }
+            };
+            if slot.is_some() {
+                return Err(RuleStringError::new(
+                    rule,
+                    format!("the `{}` part is given twice", name),
+                ));
+            }
+            *slot = Some(parse_counts(rule, rest)?);
+        }
+        (
+            survive.ok_or_else(|| error("missing the `S` part"))?,
+            birth.ok_or_else(|| error("missing the `B` part"))?,
+            states.unwrap_or(2),
+        )
+    } else {
+        let states = match parts.get(2) {
+            Some(states) => parse_states(rule, states)?,
+            None => 2,
+        };
+        (
+            parse_counts(rule, parts[0])?,
+            parse_counts(rule, parts[1])?,
+            states,
+        )
+    };
+
+    let notation = if states > 2 {
+        format!("B{}/S{}/C{}", digits(&birth), digits(&survive), states)
+    } else {
+        format!("B{}/S{}", digits(&birth), digits(&survive))
+    };
+
+    Ok(Rule {
+        notation: Some(notation),
+        ..rulesets::generations(&survive, &birth, states).with_neighborhood(Neighborhood::Moore2D)
+    })
+}
+
+fn digits(counts: &[u32]) -> String {
+    counts.iter().map(|count| count.to_string()).collect()
+}
+
+/**
+ * Write sorted counts as a 3D count list, collapsing runs into ranges, e.g. `5-7,12,13`.
+ */
+fn count_ranges(counts: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for count in counts {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *count => *end = *count,
+            _ => ranges.push((*count, *count)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| match end - start {
+            0 => start.to_string(),
+            1 => format!("{},{}", start, end),
+            _ => format!("{}-{}", start, end),
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/**
+ * Parse a 3D neighbor count list such as `5-7,12-13,15` into its counts. An empty list has no
+ * counts.
+ */
+fn parse_count_ranges(
+    rule: &str,
+    list: &str,
+    max_neighbors: u32,
+) -> Result<Vec<u32>, RuleStringError> {
+    let number = |text: &str| {
+        let count = text.trim().parse::<u32>().map_err(|_| {
+            RuleStringError::new(rule, format!("`{}` is not a neighbor count", text))
+        })?;
+        if count > max_neighbors {
+            return Err(RuleStringError::new(
+                rule,
+                format!(
+                    "{} is more neighbors than a cell has (at most {})",
+                    count, max_neighbors
+                ),
+            ));
+        }
+        Ok(count)
+    };
+
+    let mut counts = Vec::new();
+    for item in list
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+    {
+        match item.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (number(start)?, number(end)?);
+                if start > end {
+                    return Err(RuleStringError::new(
+                        rule,
+                        format!("the range `{}` is empty", item),
+                    ));
+                }
+                counts.extend(start..=end);
+            }
+            None => counts.push(number(item)?),
+        }
+    }
+    counts.sort_unstable();
+    counts.dedup();
+    Ok(counts)
+}
+
+/**
+ * Parse a 3D outer totalistic rule in `survival/birth/states/neighborhood` notation, e.g.
+ * `4/4/5/M` or `9-26/5-7,12-13,15/5/M`. Survival and birth are comma separated lists of neighbor
+ * counts or inclusive ranges, states counts the dead state too, and the neighborhood is `M` for
+ * the 26 cell Moore neighborhood or `N` (or `VN`) for the 6 cell von Neumann neighborhood. With
+ * more than two states, cells that fail to survive decay as described in `rulesets::generations`.
+ * The rule's notation is the canonical form of the rule string.
+ */
+pub fn parse_rule_string_3d(rule: &str) -> Result<Rule, RuleStringError> {
+    let parts: Vec<&str> = rule.trim().split('/').map(str::trim).collect();
+    if parts.len() != 4 {
+        return Err(RuleStringError::new(
+            rule,
+            "expected four parts: survival/birth/states/neighborhood".to_string(),
+        ));
+    }
+
+    let neighborhood = match parts[3].to_ascii_uppercase().as_str() {
+        "M" => Neighborhood::Moore3D,
+        "N" | "VN" => Neighborhood::VonNeumann3D,
+        other => {
+            return Err(RuleStringError::new(
+                rule,
+                format!("unknown neighborhood `{}`, expected `M` or `N`", other),
+            ))
+        }
+    };
+
+    let max_neighbors = neighborhood.max_neighbors();
+    let survive = parse_count_ranges(rule, parts[0], max_neighbors)?;
+    let birth = parse_count_ranges(rule, parts[1], max_neighbors)?;
+    let states = parse_states(rule, parts[2])?;
+
+    let notation = format!(
+        "{}/{}/{}/{}",
+        count_ranges(&survive),
+        count_ranges(&birth),
+        states,
+        if neighborhood == Neighborhood::Moore3D {
+            "M"
+        } else {
+            "N"
+        }
+    );
+
+    Ok(Rule {
+        notation: Some(notation),
+        ..rulesets::generations(&survive, &birth, states).with_neighborhood(neighborhood)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Which neighbor counts make a dead cell alive and keep an alive cell alive */
+    fn birth_and_survival(rule: &Rule) -> (Vec<u32>, Vec<u32>) {
+        let max_neighbors = rule.neighborhood.max_neighbors();
+        let birth = (0..=max_neighbors)
+            .filter(|n| rule.eval(0, *n) == rule.alive_state)
+            .collect();
+        let survive = (0..=max_neighbors)
+            .filter(|n| rule.eval(rule.alive_state, *n) == rule.alive_state)
+            .collect();
+        (birth, survive)
+    }
+
+    #[test]
+    fn parses_life_like_notations() {
+        for notation in ["B3/S23", "S23/B3", "b3/s23", "23/3", " B3 / S23 "] {
+            let rule = parse_rule_string(notation).unwrap();
+            assert_eq!(rule.states, 2);
+            assert_eq!(rule.neighborhood, Neighborhood::Moore2D);
+            assert_eq!(rule.notation.as_deref(), Some("B3/S23"));
+            assert_eq!(birth_and_survival(&rule), (vec![3], vec![2, 3]));
+        }
+        assert_eq!(
+            parse_rule_string("B3/S23").unwrap().statement,
+            rulesets::conways_game_of_life()
+        );
+    }
+
+    #[test]
+    fn parses_generations_notations() {
+        for notation in ["B2/S/C3", "/2/3"] {
+            let rule = parse_rule_string(notation).unwrap();
+            assert_eq!(rule.states, 3);
+            assert_eq!(rule.notation.as_deref(), Some("B2/S/C3"));
+            assert_eq!(birth_and_survival(&rule), (vec![2], vec![]));
+            // A cell that does not survive decays through the remaining states
+            assert_eq!(rule.eval(1, 0), 2);
+            assert_eq!(rule.eval(2, 2), 0);
+        }
+        let star_wars = parse_rule_string("345/2/4").unwrap();
+        assert_eq!(star_wars.notation.as_deref(), Some("B2/S345/C4"));
+    }
+
+    #[test]
+    fn rejects_invalid_rule_strings() {
+        for notation in [
+            "",
+            "B3",
+            "B3/S23/C3/X",
+            "B9/S23",
+            "Bx/S23",
+            "B3/S23/C1",
+            "B3/S23/C",
+        ] {
+            assert!(
+                parse_rule_string(notation).is_err(),
+                "{} should be rejected",
+                notation
+            );
+        }
+    }
+
+    #[test]
+    fn parses_3d_notations() {
+        let rule = parse_rule_string_3d("4/4/5/M").unwrap();
+        assert_eq!(rule.states, 5);
+        assert_eq!(rule.neighborhood, Neighborhood::Moore3D);
+        assert_eq!(rule.notation.as_deref(), Some("4/4/5/M"));
+        assert_eq!(birth_and_survival(&rule), (vec![4], vec![4]));
+
+        let rule = parse_rule_string_3d("9-26/5-7,12-13,15/5/m").unwrap();
+        assert_eq!(rule.notation.as_deref(), Some("9-26/5-7,12,13,15/5/M"));
+        assert_eq!(
+            birth_and_survival(&rule),
+            (vec![5, 6, 7, 12, 13, 15], (9..=26).collect())
+        );
+
+        for notation in ["0-6/1,3/2/N", "0-6/1,3/2/VN"] {
+            let rule = parse_rule_string_3d(notation).unwrap();
+            assert_eq!(rule.neighborhood, Neighborhood::VonNeumann3D);
+            assert_eq!(rule.notation.as_deref(), Some("0-6/1,3/2/N"));
+        }
+
+        let rule = parse_rule_string_3d("/2,3/3/M").unwrap();
+        assert_eq!(rule.notation.as_deref(), Some("/2,3/3/M"));
+        assert_eq!(birth_and_survival(&rule), (vec![2, 3], vec![]));
+    }
+
+    #[test]
+    fn rejects_invalid_3d_rule_strings() {
+        for notation in [
+            "4/4/5",
+            "4/4/5/M/M",
+            "4/4/5/X",
+            "4/7-5/5/M",
+            "4/27/5/M",
+            "7/4/5/N",
+            "4/a/5/M",
+            "4/4/1/M",
+        ] {
+            assert!(
+                parse_rule_string_3d(notation).is_err(),
+                "{} should be rejected",
+                notation
+            );
+        }
+    }
+}
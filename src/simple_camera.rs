@@ -16,6 +16,12 @@ pub struct SimpleCamera {
     pub s_down: f32,
 }
 
+impl Default for SimpleCamera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SimpleCamera {
     pub fn new() -> Self {
         Self {
@@ -54,8 +60,8 @@ impl SimpleCamera {
 
     pub fn update(&mut self, elapsed: f32) {
         let distance = 45. * elapsed;
-        let x_off_delta = (self.a_down * distance) + (self.d_down * -1. * distance);
-        let y_off_delta = (self.w_down * distance * -1.) + (self.s_down * distance);
+        let x_off_delta = (self.a_down * distance) - (self.d_down * distance);
+        let y_off_delta = (self.s_down * distance) - (self.w_down * distance);
         self.x_off += x_off_delta;
         self.y_off += y_off_delta;
     }
@@ -0,0 +1,93 @@
+use crate::automata::Automata;
+use crate::automata_error::AutomataError;
+use crate::cpu_automata::CpuAutomata;
+use glam::UVec3;
+use wgpu::{Device, Queue};
+
+/**
+ * A backend that runs an automata. Cells are always one u32 per cell in id order (x, then y, then
+ * z), so backends can be swapped at runtime and their results compared directly.
+ */
+pub trait Simulator {
+    fn dim(&self) -> UVec3;
+
+    /**
+     * The number of generations simulated so far.
+     */
+    fn iteration(&self) -> usize;
+
+    fn step(&mut self, generations: usize);
+
+    fn read_cells(&self) -> Vec<u32>;
+
+    /**
+     * Overwrite the state of every cell. The iteration count is left alone. Fails, leaving the
+     * cells untouched, if `cells` does not fit the grid and rule.
+     */
+    fn write_cells(&mut self, cells: &[u32]) -> Result<(), AutomataError>;
+}
+
+/**
+ * An `Automata` along with the device and queue it runs on.
+ */
+pub struct GpuSimulator<'a> {
+    pub automata: Automata,
+    pub device: &'a Device,
+    pub queue: &'a Queue,
+}
+
+impl<'a> GpuSimulator<'a> {
+    pub fn new(automata: Automata, device: &'a Device, queue: &'a Queue) -> Self {
+        Self {
+            automata,
+            device,
+            queue,
+        }
+    }
+}
+
+impl Simulator for GpuSimulator<'_> {
+    fn dim(&self) -> UVec3 {
+        self.automata.dim
+    }
+
+    fn iteration(&self) -> usize {
+        self.automata.iteration
+    }
+
+    fn step(&mut self, generations: usize) {
+        self.automata.step(self.device, self.queue, generations);
+    }
+
+    fn read_cells(&self) -> Vec<u32> {
+        self.automata.read_state(self.device, self.queue)
+    }
+
+    fn write_cells(&mut self, cells: &[u32]) -> Result<(), AutomataError> {
+        self.automata.write_state(self.queue, cells)
+    }
+}
+
+impl Simulator for CpuAutomata {
+    fn dim(&self) -> UVec3 {
+        self.dim
+    }
+
+    fn iteration(&self) -> usize {
+        self.iteration
+    }
+
+    fn step(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.update();
+        }
+    }
+
+    fn read_cells(&self) -> Vec<u32> {
+        self.cells.clone()
+    }
+
+    fn write_cells(&mut self, cells: &[u32]) -> Result<(), AutomataError> {
+        self.write_state(cells)
+    }
+}
@@ -0,0 +1,484 @@
+use crate::automata_dsl::{Rule, Statement};
+use crate::automata_error::AutomataError;
+use crate::boundary::{Boundaries, Boundary};
+use crate::neighborhood::Neighborhood;
+use glam::{IVec3, UVec3};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/* Every snapshot starts with these bytes */
+const SNAPSHOT_MAGIC: &[u8; 4] = b"CASN";
+
+/* The version written by `Snapshot::write_to`, bumped whenever the layout changes */
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/* Reading stops at strings and lists longer than this rather than allocating for corrupt data */
+const MAX_SNAPSHOT_LENGTH: u32 = 1 << 24;
+
+/**
+ * A snapshot that could not be read or loaded.
+ */
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /* The data does not start with the snapshot magic bytes */
+    NotASnapshot,
+    UnsupportedVersion { version: u32, supported: u32 },
+    /* The snapshot is for a grid of a different size than the automata it is loaded into */
+    DimensionMismatch { expected: UVec3, found: UVec3 },
+    /* The snapshot uses a different rule or boundaries than the automata it is loaded into */
+    SettingsMismatch(String),
+    Corrupt(String),
+    /* The snapshot was read but an automata could not be created from it */
+    Automata(AutomataError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "could not read or write the snapshot: {}", err),
+            SnapshotError::NotASnapshot => write!(f, "the data is not an automata snapshot"),
+            SnapshotError::UnsupportedVersion { version, supported } => write!(
+                f,
+                "the snapshot is version {} but only version {} is supported",
+                version, supported
+            ),
+            SnapshotError::DimensionMismatch { expected, found } => write!(
+                f,
+                "the snapshot is for a grid of {} but the automata is {}",
+                found, expected
+            ),
+            SnapshotError::SettingsMismatch(message) => write!(f, "{}", message),
+            SnapshotError::Corrupt(message) => write!(f, "the snapshot is corrupt: {}", message),
+            SnapshotError::Automata(err) => write!(f, "could not resume the snapshot: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+impl From<AutomataError> for SnapshotError {
+    fn from(err: AutomataError) -> Self {
+        SnapshotError::Automata(err)
+    }
+}
+
+/**
+ * Everything needed to resume a simulation: the grid, how far it has run, the seed it started
+ * from, its rule and boundaries and the state of every cell.
+ *
+ * The binary layout is little endian:
+ *
+ *  - the magic bytes `CASN` and the version as a u32
+ *  - the dimensions as three u32s and the iteration as a u64
+ *  - a u8 that is 1 if a u64 seed follows and 0 otherwise
+ *  - one u8 per axis for the boundaries (0 skip, 1 wrap, 2 reflect, 3 dead, 4 alive)
+ *  - the neighborhood as a u8 tag in declaration order, followed by its radius as a u32 or, for
+ *    `Offsets`, a u32 count and three i32s per offset
+ *  - the rule's states and alive state as u32s, its notation (a u8 presence flag and a string)
+ *    and its statement in the textual DSL, where a string is a u32 length and UTF-8 bytes
+ *  - the cells in id order as a u32 run count followed by (length, state) u32 pairs
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub dim: UVec3,
+    pub iteration: usize,
+    pub seed: Option<u64>,
+    pub boundaries: Boundaries,
+    pub rule: Rule,
+    pub cells: Vec<u32>,
+}
+
+fn write_u8(writer: &mut impl Write, value: u8) -> io::Result<()> {
+    writer.write_all(&[value])
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_u64(writer: &mut impl Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_string(writer: &mut impl Write, value: &str) -> io::Result<()> {
+    write_u32(writer, value.len() as u32)?;
+    writer.write_all(value.as_bytes())
+}
+
+/* Running out of data means the snapshot was cut short */
+fn read_error(err: io::Error) -> SnapshotError {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => {
+            SnapshotError::Corrupt("the snapshot ends early".to_string())
+        }
+        _ => SnapshotError::Io(err),
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], SnapshotError> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes).map_err(read_error)?;
+    Ok(bytes)
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8, SnapshotError> {
+    Ok(read_bytes::<1>(reader)?[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, SnapshotError> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, SnapshotError> {
+    Ok(u64::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_length(reader: &mut impl Read, what: &str) -> Result<u32, SnapshotError> {
+    let length = read_u32(reader)?;
+    if length > MAX_SNAPSHOT_LENGTH {
+        return Err(SnapshotError::Corrupt(format!(
+            "the {} is {} long",
+            what, length
+        )));
+    }
+    Ok(length)
+}
+
+fn read_string(reader: &mut impl Read, what: &str) -> Result<String, SnapshotError> {
+    let length = read_length(reader, what)?;
+    let mut bytes = vec![0; length as usize];
+    reader.read_exact(&mut bytes).map_err(read_error)?;
+    String::from_utf8(bytes)
+        .map_err(|_| SnapshotError::Corrupt(format!("the {} is not valid UTF-8", what)))
+}
+
+const BOUNDARIES: [Boundary; 5] = [
+    Boundary::Skip,
+    Boundary::Wrap,
+    Boundary::Reflect,
+    Boundary::Dead,
+    Boundary::Alive,
+];
+
+fn read_boundary(reader: &mut impl Read) -> Result<Boundary, SnapshotError> {
+    let tag = read_u8(reader)?;
+    BOUNDARIES
+        .get(tag as usize)
+        .copied()
+        .ok_or_else(|| SnapshotError::Corrupt(format!("unknown boundary {}", tag)))
+}
+
+fn write_neighborhood(writer: &mut impl Write, neighborhood: &Neighborhood) -> io::Result<()> {
+    let (tag, radius) = match neighborhood {
+        Neighborhood::Moore2D => (0, None),
+        Neighborhood::VonNeumann2D => (1, None),
+        Neighborhood::Moore3D => (2, None),
+        Neighborhood::VonNeumann3D => (3, None),
+        Neighborhood::Box2D(radius) => (4, Some(*radius)),
+        Neighborhood::Box3D(radius) => (5, Some(*radius)),
+        Neighborhood::Diamond2D(radius) => (6, Some(*radius)),
+        Neighborhood::Diamond3D(radius) => (7, Some(*radius)),
+        Neighborhood::Offsets(offsets) => {
+            write_u8(writer, 8)?;
+            write_u32(writer, offsets.len() as u32)?;
+            for offset in offsets {
+                for component in offset.to_array() {
+                    writer.write_all(&component.to_le_bytes())?;
+                }
+            }
+            return Ok(());
+        }
+    };
+
+    write_u8(writer, tag)?;
+    match radius {
+        Some(radius) => write_u32(writer, radius),
+        None => Ok(()),
+    }
+}
+
+fn read_neighborhood(reader: &mut impl Read) -> Result<Neighborhood, SnapshotError> {
+    let neighborhood = match read_u8(reader)? {
+        0 => Neighborhood::Moore2D,
+        1 => Neighborhood::VonNeumann2D,
+        2 => Neighborhood::Moore3D,
+        3 => Neighborhood::VonNeumann3D,
+        4 => Neighborhood::Box2D(read_u32(reader)?),
+        5 => Neighborhood::Box3D(read_u32(reader)?),
+        6 => Neighborhood::Diamond2D(read_u32(reader)?),
+        7 => Neighborhood::Diamond3D(read_u32(reader)?),
+        8 => {
+            let count = read_length(reader, "neighborhood")?;
+            let mut offsets = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let mut component =
+                    || Ok::<i32, SnapshotError>(i32::from_le_bytes(read_bytes(reader)?));
+                offsets.push(IVec3::new(component()?, component()?, component()?));
+            }
+            Neighborhood::Offsets(offsets)
+        }
+        tag => {
+            return Err(SnapshotError::Corrupt(format!(
+                "unknown neighborhood {}",
+                tag
+            )))
+        }
+    };
+    Ok(neighborhood)
+}
+
+impl Snapshot {
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        write_u32(writer, SNAPSHOT_VERSION)?;
+
+        for size in self.dim.to_array() {
+            write_u32(writer, size)?;
+        }
+        write_u64(writer, self.iteration as u64)?;
+
+        match self.seed {
+            Some(seed) => {
+                write_u8(writer, 1)?;
+                write_u64(writer, seed)?;
+            }
+            None => write_u8(writer, 0)?,
+        }
+
+        for boundary in [self.boundaries.x, self.boundaries.y, self.boundaries.z] {
+            let tag = BOUNDARIES.iter().position(|b| *b == boundary).unwrap();
+            write_u8(writer, tag as u8)?;
+        }
+
+        write_neighborhood(writer, &self.rule.neighborhood)?;
+        write_u32(writer, self.rule.states)?;
+        write_u32(writer, self.rule.alive_state)?;
+        match &self.rule.notation {
+            Some(notation) => {
+                write_u8(writer, 1)?;
+                write_string(writer, notation)?;
+            }
+            None => write_u8(writer, 0)?,
+        }
+        write_string(writer, &self.rule.statement.to_string())?;
+
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        for cell in &self.cells {
+            match runs.last_mut() {
+                Some((length, state)) if state == cell => *length += 1,
+                _ => runs.push((1, *cell)),
+            }
+        }
+        write_u32(writer, runs.len() as u32)?;
+        for (length, state) in runs {
+            write_u32(writer, length)?;
+            write_u32(writer, state)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> Result<Self, SnapshotError> {
+        if &read_bytes::<4>(reader).map_err(|_| SnapshotError::NotASnapshot)? != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+
+        let version = read_u32(reader)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion {
+                version,
+                supported: SNAPSHOT_VERSION,
+            });
+        }
+
+        let dim = UVec3::new(read_u32(reader)?, read_u32(reader)?, read_u32(reader)?);
+        let size = dim.x as u64 * dim.y as u64 * dim.z as u64;
+        if size > u32::MAX as u64 {
+            return Err(SnapshotError::Corrupt(format!(
+                "a grid of {} is too large",
+                dim
+            )));
+        }
+        let iteration = read_u64(reader)? as usize;
+
+        let seed = match read_u8(reader)? {
+            0 => None,
+            _ => Some(read_u64(reader)?),
+        };
+
+        let boundaries = Boundaries {
+            x: read_boundary(reader)?,
+            y: read_boundary(reader)?,
+            z: read_boundary(reader)?,
+        };
+
+        let neighborhood = read_neighborhood(reader)?;
+        let states = read_u32(reader)?;
+        let alive_state = read_u32(reader)?;
+        let notation = match read_u8(reader)? {
+            0 => None,
+            _ => Some(read_string(reader, "rule notation")?),
+        };
+        let statement = read_string(reader, "rule")?
+            .parse::<Statement>()
+            .map_err(|err| SnapshotError::Corrupt(format!("the rule does not parse: {}", err)))?;
+        let rule = Rule {
+            statement,
+            states,
+            alive_state,
+            neighborhood,
+            notation,
+        };
+        rule.validate()
+            .map_err(|err| SnapshotError::Corrupt(format!("the rule is invalid: {}", err)))?;
+
+        let runs = read_u32(reader)?;
+        let mut cells = Vec::new();
+        for _ in 0..runs {
+            let (length, state) = (read_u32(reader)?, read_u32(reader)?);
+            if cells.len() as u64 + length as u64 > size {
+                return Err(SnapshotError::Corrupt(format!(
+                    "there are more cells than the {} cells of a grid of {}",
+                    size, dim
+                )));
+            }
+            if state > rule.max_state() {
+                return Err(SnapshotError::Corrupt(format!(
+                    "a cell is in state {} but the last state of the rule is {}",
+                    state,
+                    rule.max_state()
+                )));
+            }
+            cells.extend(std::iter::repeat_n(state, length as usize));
+        }
+        if cells.len() as u64 != size {
+            return Err(SnapshotError::Corrupt(format!(
+                "there are {} cells but a grid of {} has {}",
+                cells.len(),
+                dim,
+                size
+            )));
+        }
+
+        Ok(Self {
+            dim,
+            iteration,
+            seed,
+            boundaries,
+            rule,
+            cells,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule_string::{parse_rule_string, parse_rule_string_3d};
+
+    fn snapshot() -> Snapshot {
+        let dim = UVec3::new(5, 4, 3);
+        Snapshot {
+            dim,
+            iteration: 42,
+            seed: Some(7),
+            boundaries: Boundaries {
+                x: Boundary::Wrap,
+                y: Boundary::Reflect,
+                z: Boundary::Dead,
+            },
+            rule: parse_rule_string_3d("4/4/5/M").unwrap(),
+            cells: (0..dim.x * dim.y * dim.z).map(|id| id / 7 % 5).collect(),
+        }
+    }
+
+    fn bytes(snapshot: &Snapshot) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        snapshot.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let snapshot = snapshot();
+        assert_eq!(
+            Snapshot::read_from(&mut &bytes(&snapshot)[..]).unwrap(),
+            snapshot
+        );
+
+        let left = IVec3::new(-1, 0, 0);
+        let snapshot = Snapshot {
+            dim: UVec3::new(3, 2, 1),
+            seed: None,
+            boundaries: Boundaries::all(Boundary::Alive),
+            rule: parse_rule_string("B1/S1")
+                .unwrap()
+                .with_neighborhood(Neighborhood::Offsets(vec![left, -left])),
+            cells: vec![0, 1, 1, 0, 0, 0],
+            ..snapshot
+        };
+        assert_eq!(
+            Snapshot::read_from(&mut &bytes(&snapshot)[..]).unwrap(),
+            snapshot
+        );
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        let mut bytes = bytes(&snapshot());
+        bytes[0] = b'X';
+        assert!(matches!(
+            Snapshot::read_from(&mut &bytes[..]),
+            Err(SnapshotError::NotASnapshot)
+        ));
+        assert!(matches!(
+            Snapshot::read_from(&mut &b"CA"[..]),
+            Err(SnapshotError::NotASnapshot)
+        ));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = bytes(&snapshot());
+        bytes[4..8].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Snapshot::read_from(&mut &bytes[..]),
+            Err(SnapshotError::UnsupportedVersion { version, supported })
+                if version == SNAPSHOT_VERSION + 1 && supported == SNAPSHOT_VERSION
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_snapshots() {
+        let bytes = bytes(&snapshot());
+        for length in 4..bytes.len() {
+            assert!(
+                matches!(
+                    Snapshot::read_from(&mut &bytes[..length]),
+                    Err(SnapshotError::Corrupt(_))
+                ),
+                "a snapshot cut at {} bytes should be corrupt",
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_cells_past_the_last_state() {
+        let snapshot = Snapshot {
+            cells: vec![5; snapshot().cells.len()],
+            ..snapshot()
+        };
+        assert!(matches!(
+            Snapshot::read_from(&mut &bytes(&snapshot)[..]),
+            Err(SnapshotError::Corrupt(_))
+        ));
+    }
+}
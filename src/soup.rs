@@ -0,0 +1,79 @@
+use glam::UVec3;
+
+/**
+ * The SplitMix64 generator (Steele, Lea and Flood, "Fast Splittable Pseudorandom Number
+ * Generators"). It is used instead of `rand` because its output is fully specified by the
+ * algorithm, so a seed gives the same sequence on every platform and with every version of
+ * this crate.
+ */
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /**
+     * A uniform float in [0, 1) built from the top 24 bits of `next_u64`. Every such value is
+     * exactly representable as an f32, so comparisons against it never depend on rounding.
+     */
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u32 << 24) as f32
+    }
+}
+
+/**
+ * A random initial state for a grid of `dim` cells where each cell is in `alive_state` with
+ * probability `p` and dead otherwise. Cells are drawn in id order (x, then y, then z) from a
+ * `SplitMix64` seeded with `seed`, one number per cell, so the same seed and dimensions always
+ * give the same soup. Panics if the grid has more than `u32::MAX` cells, which the automata
+ * constructors reject with an error before generating a soup.
+ */
+pub fn random_soup(dim: &UVec3, p: f32, seed: u64, alive_state: u32) -> Vec<u32> {
+    let cells = dim
+        .x
+        .checked_mul(dim.y)
+        .and_then(|cells| cells.checked_mul(dim.z))
+        .unwrap_or_else(|| panic!("a grid of {} has more than u32::MAX cells", dim));
+    let mut rng = SplitMix64::new(seed);
+    (0..cells)
+        .map(|_| if rng.next_f32() < p { alive_state } else { 0 })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_reference_sequence() {
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xe220a8397b1dcdaf);
+        assert_eq!(rng.next_u64(), 0x6e789e6aa1b965f4);
+    }
+
+    #[test]
+    fn soups_are_seeded_and_use_the_alive_state() {
+        let dim = UVec3::new(16, 8, 4);
+        let soup = random_soup(&dim, 0.4, 9, 3);
+        assert_eq!(soup.len(), 16 * 8 * 4);
+        assert_eq!(soup, random_soup(&dim, 0.4, 9, 3));
+        assert_ne!(soup, random_soup(&dim, 0.4, 10, 3));
+        assert!(soup.iter().all(|cell| *cell == 0 || *cell == 3));
+        assert!(soup.contains(&0) && soup.contains(&3));
+
+        assert!(random_soup(&dim, 0.0, 9, 3).iter().all(|cell| *cell == 0));
+        assert!(random_soup(&dim, 1.0, 9, 3).iter().all(|cell| *cell == 3));
+    }
+}